@@ -0,0 +1,35 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Compile the configs in `.i/` into the binary, so an installed `i` has them
+// wherever it runs. Writes `BUNDLED`, a list of (name, JSON) pairs, to
+// `$OUT_DIR/bundled.rs` for `config.rs` to include.
+fn main() {
+    println!("cargo:rerun-if-changed=.i");
+
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join(".i");
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+
+    let mut out = String::from("const BUNDLED: &[(&str, &str)] = &[\n");
+    for path in &files {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        out.push_str(&format!(
+            "    ({:?}, include_str!({:?})),\n",
+            name,
+            path.display().to_string()
+        ));
+    }
+    out.push_str("];\n");
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("bundled.rs"), out).unwrap();
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+
+/// How long fetched placeholder values are reused when a config doesn't say
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+const CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    command: String,
+    fetched_at: u64, // Seconds since the Unix epoch
+    values: Vec<(String, String)>,
+}
+
+impl Entry {
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        now().saturating_sub(self.fetched_at) < ttl.as_secs()
+    }
+}
+
+/// Placeholder fetch results keyed by the fully expanded fetch command.
+/// Lives for the whole `i` session; entries can also be written under
+/// the user's cache dir so they survive between runs.
+pub struct FetchCache {
+    entries: LruCache<String, Entry>,
+    dir: Option<PathBuf>,
+}
+
+impl Default for FetchCache {
+    fn default() -> Self {
+        Self::new(dirs::cache_dir().map(|dir| dir.join("i").join("fetch")))
+    }
+}
+
+impl FetchCache {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            entries: LruCache::new(NonZeroUsize::new(CAPACITY).unwrap()),
+            dir,
+        }
+    }
+
+    /// Cached values for `command` if younger than `ttl`. With `persist`,
+    /// falls back to the on-disk cache.
+    pub fn get(
+        &mut self,
+        command: &str,
+        ttl: Duration,
+        persist: bool,
+    ) -> Option<Vec<(String, String)>> {
+        if ttl.is_zero() {
+            return None;
+        }
+
+        if let Some(entry) = self.entries.get(command) {
+            if entry.is_fresh(ttl) {
+                return Some(entry.values.clone());
+            }
+        }
+
+        if !persist {
+            return None;
+        }
+        let entry = self.read_disk(command)?;
+        if !entry.is_fresh(ttl) {
+            return None;
+        }
+        let values = entry.values.clone();
+        self.entries.put(command.to_string(), entry);
+        Some(values)
+    }
+
+    pub fn insert(&mut self, command: &str, values: Vec<(String, String)>, persist: bool) {
+        let entry = Entry {
+            command: command.to_string(),
+            fetched_at: now(),
+            values,
+        };
+        if persist {
+            // The on-disk cache is best-effort; a failed write just means a refetch
+            let _ = self.write_disk(&entry);
+        }
+        self.entries.put(command.to_string(), entry);
+    }
+
+    /// Forget `command` so the next lookup runs it again
+    pub fn invalidate(&mut self, command: &str) {
+        self.entries.pop(command);
+        if let Some(path) = self.disk_path(command) {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    fn disk_path(&self, command: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        Some(dir.join(format!("{:016x}.json", fnv1a(command))))
+    }
+
+    fn read_disk(&self, command: &str) -> Option<Entry> {
+        let content = fs::read_to_string(self.disk_path(command)?).ok()?;
+        let entry: Entry = serde_json::from_str(&content).ok()?;
+        // Guard against hash collisions
+        (entry.command == command).then_some(entry)
+    }
+
+    fn write_disk(&self, entry: &Entry) -> std::io::Result<()> {
+        let Some(path) = self.disk_path(&entry.command) else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(entry)?)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Stable hash for cache file names (std's hasher may change between releases)
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Vec<(String, String)> {
+        vec![("web".to_string(), "1".to_string())]
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("i-cache-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_get_returns_inserted_values() {
+        let mut cache = FetchCache::new(None);
+        cache.insert("kubectl get pods", values(), false);
+        assert_eq!(
+            cache.get("kubectl get pods", DEFAULT_TTL, false),
+            Some(values())
+        );
+        assert_eq!(cache.get("kubectl get ns", DEFAULT_TTL, false), None);
+    }
+
+    #[test]
+    fn test_zero_ttl_disables_cache() {
+        let mut cache = FetchCache::new(None);
+        cache.insert("docker ps", values(), false);
+        assert_eq!(cache.get("docker ps", Duration::ZERO, false), None);
+    }
+
+    #[test]
+    fn test_stale_entries_are_ignored() {
+        let mut cache = FetchCache::new(None);
+        cache.entries.put(
+            "docker ps".to_string(),
+            Entry {
+                command: "docker ps".to_string(),
+                fetched_at: now() - 120,
+                values: values(),
+            },
+        );
+        assert_eq!(cache.get("docker ps", DEFAULT_TTL, false), None);
+        assert!(cache
+            .get("docker ps", Duration::from_secs(300), false)
+            .is_some());
+    }
+
+    #[test]
+    fn test_invalidate_forgets_entry() {
+        let mut cache = FetchCache::new(None);
+        cache.insert("docker ps", values(), false);
+        cache.invalidate("docker ps");
+        assert_eq!(cache.get("docker ps", DEFAULT_TTL, false), None);
+    }
+
+    #[test]
+    fn test_persisted_entries_survive_new_cache() {
+        let dir = temp_dir("persist");
+        FetchCache::new(Some(dir.clone())).insert("docker images", values(), true);
+
+        let mut cache = FetchCache::new(Some(dir.clone()));
+        assert_eq!(cache.get("docker images", DEFAULT_TTL, false), None);
+        assert_eq!(
+            cache.get("docker images", DEFAULT_TTL, true),
+            Some(values())
+        );
+
+        cache.invalidate("docker images");
+        let mut cache = FetchCache::new(Some(dir.clone()));
+        assert_eq!(cache.get("docker images", DEFAULT_TTL, true), None);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,648 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::config::{self, Config, StepType};
+
+/// A problem found in a config file, at a line and column (both from 1)
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub help: Option<String>,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            self.message
+        )?;
+        if let Some(help) = &self.help {
+            write!(f, "\n  help: {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check config files for mistakes that load without an error but make a
+/// broken wizard. Chains may lead to any config there is, or to one of
+/// `paths`. A file that's one of a config's layers is checked together
+/// with the layers below it; any other file is checked on its own.
+pub fn check_files(paths: &[PathBuf]) -> Vec<Diagnostic> {
+    let mut configs = config::config_names();
+    configs.extend(paths.iter().filter_map(|path| file_name(path)));
+
+    let mut diagnostics = Vec::new();
+    for path in paths {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let below = config::load_below(path);
+                diagnostics.extend(check(path, &text, &configs, below.as_ref()))
+            }
+            Err(e) => diagnostics.push(Diagnostic {
+                path: path.clone(),
+                line: 1,
+                column: 1,
+                message: format!("can't read the file: {}", e),
+                help: None,
+            }),
+        }
+    }
+    diagnostics
+}
+
+/// The config name a file stands for, e.g. `docker-run` for `docker-run.json`
+fn file_name(path: &Path) -> Option<String> {
+    Some(path.file_stem()?.to_str()?.to_string())
+}
+
+/// A step as `when` sees it: its type and option labels
+struct StepInfo {
+    step_type: String,
+    labels: Vec<String>,
+}
+
+/// Check one config file's text. A file may add to `below`, the config from
+/// the layers under it, so that config's steps and placeholders count too.
+pub fn check(
+    path: &Path,
+    text: &str,
+    configs: &BTreeSet<String>,
+    below: Option<&Config>,
+) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        path,
+        text,
+        diagnostics: Vec::new(),
+    };
+
+    if let Err(e) = serde_json::from_str::<Value>(text).and_then(|_| config::parse_layer(text)) {
+        checker.diagnostics.push(Diagnostic {
+            path: path.to_path_buf(),
+            line: e.line().max(1),
+            column: e.column().max(1),
+            message: strip_position(&e.to_string()),
+            help: None,
+        });
+        return checker.diagnostics;
+    }
+    let Ok(root @ Value::Object(_)) = serde_json::from_str::<Value>(text) else {
+        return checker.diagnostics;
+    };
+
+    let steps = array(&root, "steps");
+
+    // Steps `when` can refer to: this file's, then the ones below it
+    let mut known: HashMap<String, StepInfo> = HashMap::new();
+    for step in below.iter().flat_map(|config| &config.steps) {
+        let step_type = match step.step_type {
+            StepType::Choice => "choice",
+            StepType::Toggle => "toggle",
+            StepType::Text => "text",
+            StepType::Multi => "multi",
+        };
+        let info = StepInfo {
+            step_type: step_type.to_string(),
+            labels: step.options.iter().map(|o| o.label.clone()).collect(),
+        };
+        known.insert(step.id.clone(), info);
+    }
+    // The first of steps with the same id; later ones are reported below
+    for step in steps.iter().rev().filter(|step| !is_disabled(step)) {
+        if let Some(id) = str_field(step, "id") {
+            known.insert(id.to_string(), step_info(step));
+        }
+    }
+
+    let mut first_ids: HashMap<&str, usize> = HashMap::new();
+    for (i, step) in steps.iter().enumerate() {
+        let at = |field: &'static str| vec![Key::Field("steps"), Key::Index(i), Key::Field(field)];
+        let Some(id) = str_field(step, "id") else {
+            continue;
+        };
+        if let Some(first) = first_ids.get(id) {
+            let (line, _) = checker.position(&[Key::Field("steps"), Key::Index(*first)]);
+            checker.report(
+                &at("id"),
+                format!("duplicate step id '{}'", id),
+                Some(format!("the first step with this id is at line {}", line)),
+            );
+            continue;
+        }
+        first_ids.insert(id, i);
+        if is_disabled(step) {
+            continue;
+        }
+
+        let info = step_info(step);
+        if info.step_type == "toggle" && step.get("flag").is_none() {
+            checker.report(
+                &at("type"),
+                format!("toggle step '{}' has no flag", id),
+                Some("add a \"flag\" for when it's on, e.g. \"--verbose\"".to_string()),
+            );
+        }
+
+        if let Some(default) = step.get("default").and_then(Value::as_u64) {
+            let count = info.labels.len();
+            let has_options = matches!(info.step_type.as_str(), "choice" | "multi");
+            if has_options && default as usize >= count {
+                let help = match count {
+                    0 => "the step has no options".to_string(),
+                    _ => format!("use an index from 0 to {}", count - 1),
+                };
+                checker.report(
+                    &at("default"),
+                    format!(
+                        "default {} is out of range for step '{}', which has {} options",
+                        default, id, count
+                    ),
+                    Some(help),
+                );
+            }
+        }
+
+        if let Some(Value::Object(when)) = step.get("when") {
+            for (ref_id, expected) in when {
+                let key = [at("when"), vec![Key::Field(ref_id)]].concat();
+                checker.check_when(&key, &known, ref_id, expected);
+            }
+        }
+
+        for (j, option) in array(step, "options").iter().enumerate() {
+            let target = match option.get("chain") {
+                Some(Value::String(name)) => name,
+                Some(Value::Object(chain)) => match chain.get("config") {
+                    Some(Value::String(name)) => name,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if !configs.contains(target) {
+                let key = [at("options"), vec![Key::Index(j), Key::Field("chain")]].concat();
+                checker.report(
+                    &key,
+                    format!("chain to '{}', which has no config", target),
+                    did_you_mean(target, configs.iter().map(String::as_str)),
+                );
+            }
+        }
+    }
+
+    checker.check_placeholders(&root, below);
+    // In the order they appear in the file
+    checker.diagnostics.sort_by_key(|d| (d.line, d.column));
+    checker.diagnostics
+}
+
+/// serde_json's message without its " at line L column C", which the
+/// diagnostic shows in front
+fn strip_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(i) => message[..i].to_string(),
+        None => message.to_string(),
+    }
+}
+
+fn array<'a>(object: &'a Value, field: &str) -> &'a [Value] {
+    object
+        .get(field)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn str_field<'a>(object: &'a Value, field: &str) -> Option<&'a str> {
+    object.get(field)?.as_str()
+}
+
+fn is_disabled(step: &Value) -> bool {
+    step.get("disabled") == Some(&Value::Bool(true))
+}
+
+fn step_info(step: &Value) -> StepInfo {
+    StepInfo {
+        step_type: str_field(step, "type").unwrap_or_default().to_string(),
+        labels: array(step, "options")
+            .iter()
+            .filter_map(|o| str_field(o, "label"))
+            .map(String::from)
+            .collect(),
+    }
+}
+
+struct Checker<'a> {
+    path: &'a Path,
+    text: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn position(&self, key: &[Key]) -> (usize, usize) {
+        line_column(self.text, locate(self.text, key).unwrap_or(0))
+    }
+
+    fn report(&mut self, key: &[Key], message: String, help: Option<String>) {
+        let (line, column) = self.position(key);
+        self.diagnostics.push(Diagnostic {
+            path: self.path.to_path_buf(),
+            line,
+            column,
+            message,
+            help,
+        });
+    }
+
+    /// A `when` condition must name a step, and a value that step can have
+    fn check_when(
+        &mut self,
+        key: &[Key],
+        known: &HashMap<String, StepInfo>,
+        ref_id: &str,
+        expected: &Value,
+    ) {
+        let Some(step) = known.get(ref_id) else {
+            self.report(
+                key,
+                format!("'when' refers to step '{}', which doesn't exist", ref_id),
+                did_you_mean(ref_id, known.keys().map(String::as_str)),
+            );
+            return;
+        };
+        let value = expected.as_str().unwrap_or_default();
+        match step.step_type.as_str() {
+            "choice" | "multi" if !step.labels.iter().any(|label| label == value) => {
+                let help = did_you_mean(value, step.labels.iter().map(String::as_str))
+                    .unwrap_or_else(|| format!("options are: {}", step.labels.join(", ")));
+                self.report(
+                    key,
+                    format!("'{}' is not an option of step '{}'", value, ref_id),
+                    Some(help),
+                );
+            }
+            "toggle" if value != "true" && value != "false" => self.report(
+                key,
+                format!(
+                    "step '{}' is a toggle, so 'when' can't match '{}'",
+                    ref_id, value
+                ),
+                Some("use \"true\" or \"false\"".to_string()),
+            ),
+            _ => {}
+        }
+    }
+
+    /// Preset placeholders without a fetch are typed in when the preset is
+    /// used. One that's a near miss of a fetch nothing uses is likely a typo.
+    fn check_placeholders(&mut self, root: &Value, below: Option<&Config>) {
+        let mut fetched: BTreeSet<&str> = root
+            .get("placeholder_options")
+            .and_then(Value::as_object)
+            .map(|options| options.keys().map(String::as_str).collect())
+            .unwrap_or_default();
+        fetched.extend(
+            below
+                .iter()
+                .flat_map(|config| config.placeholder_options.keys())
+                .map(String::as_str),
+        );
+
+        // Every flag of this file and the config below it
+        let mut flags: Vec<&str> = Vec::new();
+        for preset in array(root, "presets") {
+            flags.extend(str_field(preset, "flags"));
+        }
+        for step in array(root, "steps") {
+            flags.extend(str_field(step, "flag"));
+            flags.extend(
+                array(step, "options")
+                    .iter()
+                    .filter_map(|o| str_field(o, "flag")),
+            );
+        }
+        if let Some(config) = below {
+            flags.extend(config.presets.iter().map(|p| p.flags.as_str()));
+            for step in &config.steps {
+                flags.extend(step.flag.as_deref());
+                flags.extend(step.options.iter().filter_map(|o| o.flag.as_deref()));
+            }
+        }
+        let used: BTreeSet<&str> = flags.iter().flat_map(|f| placeholders(f)).collect();
+        let unused: Vec<&str> = fetched.difference(&used).copied().collect();
+
+        for (i, preset) in array(root, "presets").iter().enumerate() {
+            let flags = str_field(preset, "flags").unwrap_or_default();
+            for placeholder in placeholders(flags) {
+                if fetched.contains(placeholder) {
+                    continue;
+                }
+                if let Some(help) = did_you_mean(placeholder, unused.iter().copied()) {
+                    let key = [Key::Field("presets"), Key::Index(i), Key::Field("flags")];
+                    self.report(
+                        &key,
+                        format!(
+                            "placeholder '{}' has no fetch in placeholder_options",
+                            placeholder
+                        ),
+                        Some(help),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The `<name>` placeholders in preset flags
+fn placeholders(flags: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = flags;
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>') else {
+            break;
+        };
+        found.push(&rest[open..=open + close]);
+        rest = &rest[open + close + 1..];
+    }
+    found
+}
+
+/// "did you mean 'x'?" for the candidate closest to `name`, if any is close
+fn did_you_mean<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+    let max = (name.chars().count() / 4).clamp(1, 2);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max)
+        .min()
+        .map(|(_, candidate)| format!("did you mean '{}'?", candidate))
+}
+
+/// Edits (insert, delete, replace, or swap two neighbours) to turn `a`
+/// into `b`, counting chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Distances from a[..i] to each b[..j], for the last two rows too
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (previous[j] + 1)
+                .min(row[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, row);
+    }
+    previous[b.len()]
+}
+
+/// A step along the way to a value in a JSON document
+#[derive(Debug, Clone, Copy)]
+enum Key<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+/// Byte offset in `text` of what `path` leads to: the key of an object
+/// member, or the start of an array element. `text` is valid JSON.
+fn locate(text: &str, path: &[Key]) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut pos = skip_whitespace(bytes, 0);
+    let mut found = pos;
+    for key in path {
+        match (key, bytes.get(pos)?) {
+            (Key::Field(name), b'{') => {
+                pos += 1;
+                loop {
+                    pos = skip_whitespace(bytes, pos);
+                    if *bytes.get(pos)? != b'"' {
+                        return None;
+                    }
+                    let key_end = skip_string(bytes, pos)?;
+                    let key: String = serde_json::from_str(&text[pos..key_end]).ok()?;
+                    let key_start = pos;
+                    // Past the `:` to the value
+                    pos = skip_whitespace(bytes, skip_whitespace(bytes, key_end) + 1);
+                    if key == *name {
+                        found = key_start;
+                        break;
+                    }
+                    pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+                    if *bytes.get(pos)? != b',' {
+                        return None;
+                    }
+                    pos += 1;
+                }
+            }
+            (Key::Index(index), b'[') => {
+                pos = skip_whitespace(bytes, pos + 1);
+                for _ in 0..*index {
+                    pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+                    if *bytes.get(pos)? != b',' {
+                        return None;
+                    }
+                    pos = skip_whitespace(bytes, pos + 1);
+                }
+                if *bytes.get(pos)? == b']' {
+                    return None;
+                }
+                found = pos;
+            }
+            _ => return None,
+        }
+    }
+    Some(found)
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+/// The offset just past the string starting at `pos`
+fn skip_string(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    pos += 1;
+    loop {
+        match bytes.get(pos)? {
+            b'\\' => pos += 2,
+            b'"' => return Some(pos + 1),
+            _ => pos += 1,
+        }
+    }
+}
+
+/// The offset just past the value starting at `pos`
+fn skip_value(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    match bytes.get(pos)? {
+        b'"' => skip_string(bytes, pos),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            loop {
+                match bytes.get(pos)? {
+                    b'"' => {
+                        pos = skip_string(bytes, pos)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(pos + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                pos += 1;
+            }
+        }
+        _ => {
+            while bytes
+                .get(pos)
+                .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+            {
+                pos += 1;
+            }
+            Some(pos)
+        }
+    }
+}
+
+/// 1-based line and column (in chars) of a byte offset
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_json(json: &str) -> Vec<String> {
+        let configs = BTreeSet::from(["docker-run".to_string()]);
+        check(Path::new("t.json"), json, &configs, None)
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_locate_keys_and_elements() {
+        let text = "{\n  \"a\": [1, {\"b\": \"}\"}],\n  \"c\": { \"d\": true }\n}";
+        let at = |path: &[Key]| line_column(text, locate(text, path).unwrap());
+        assert_eq!(at(&[Key::Field("a")]), (2, 3));
+        assert_eq!(at(&[Key::Field("a"), Key::Index(1)]), (2, 12));
+        assert_eq!(
+            at(&[Key::Field("a"), Key::Index(1), Key::Field("b")]),
+            (2, 13)
+        );
+        assert_eq!(at(&[Key::Field("c"), Key::Field("d")]), (3, 10));
+        assert_eq!(locate(text, &[Key::Field("a"), Key::Index(2)]), None);
+        assert_eq!(locate(text, &[Key::Field("x")]), None);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("mode", "mode"), 0);
+        assert_eq!(edit_distance("mdoe", "mode"), 1);
+        assert_eq!(edit_distance("prefix", "profile"), 3);
+        assert_eq!(edit_distance("detached", "Detached"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_syntax_and_type_errors_have_positions() {
+        assert_eq!(
+            check_json("{\n  \"command\": \"ls\",\n}"),
+            ["t.json:3:1: trailing comma"]
+        );
+        let errors = check_json("{ \"command\": \"ls\", \"steps\": [{ \"id\": 1 }] }");
+        assert!(errors[0].starts_with("t.json:1:"), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_semantic_problems() {
+        let json = r#"{
+  "command": "docker",
+  "steps": [
+    { "id": "mode", "prompt": "Mode?", "type": "choice", "default": 2, "options": [
+      { "label": "Detached", "flag": "-d" },
+      { "label": "Run", "chain": "docker-runn" }
+    ] },
+    { "id": "rm", "prompt": "Remove?", "type": "toggle" },
+    { "id": "name", "prompt": "Name:", "type": "text", "when": { "mdoe": "Detached" } },
+    { "id": "port", "prompt": "Port:", "type": "text", "when": { "mode": "detached", "rm": "yes" } },
+    { "id": "mode", "prompt": "Again?", "type": "text" }
+  ],
+  "presets": [{ "label": "Stop", "flags": "stop <contianer> <new_name>" }],
+  "placeholder_options": { "<container>": "docker ps --format '{{.Names}}'" }
+}"#;
+        assert_eq!(
+            check_json(json),
+            [
+                "t.json:4:58: default 2 is out of range for step 'mode', which has 2 options\n  help: use an index from 0 to 1",
+                "t.json:6:25: chain to 'docker-runn', which has no config\n  help: did you mean 'docker-run'?",
+                "t.json:8:40: toggle step 'rm' has no flag\n  help: add a \"flag\" for when it's on, e.g. \"--verbose\"",
+                "t.json:9:66: 'when' refers to step 'mdoe', which doesn't exist\n  help: did you mean 'mode'?",
+                "t.json:10:66: 'detached' is not an option of step 'mode'\n  help: did you mean 'Detached'?",
+                "t.json:10:86: step 'rm' is a toggle, so 'when' can't match 'yes'\n  help: use \"true\" or \"false\"",
+                "t.json:11:7: duplicate step id 'mode'\n  help: the first step with this id is at line 4",
+                "t.json:13:34: placeholder '<contianer>' has no fetch in placeholder_options\n  help: did you mean '<container>'?",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disabled_steps_and_layers_pass() {
+        let json = r#"{ "steps": [{ "id": "color", "disabled": true }], "presets": [] }"#;
+        assert!(check_json(json).is_empty());
+    }
+
+    #[test]
+    fn test_file_outside_the_layers_is_checked_on_its_own() {
+        // Named like a bundled config, whose `format` step mustn't count
+        let dir = std::env::temp_dir().join(format!("i-check-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ls.json");
+        let json = r#"{ "steps": [{ "id": "all", "prompt": "All?", "type": "toggle", "flag": "-a", "when": { "format": "Long" } }] }"#;
+        fs::write(&path, json).unwrap();
+
+        let messages: Vec<String> = check_files(std::slice::from_ref(&path))
+            .iter()
+            .map(|d| d.message.clone())
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            messages,
+            ["'when' refers to step 'format', which doesn't exist"]
+        );
+    }
+
+    #[test]
+    fn test_problems_on_one_line_are_in_column_order() {
+        let json = r#"{ "presets": [{ "label": "Logs", "flags": "<contianer>" }], "placeholder_options": { "<container>": "docker ps" }, "steps": [{ "id": "rm", "prompt": "Remove?", "type": "toggle" }] }"#;
+        let columns: Vec<usize> = check(Path::new("t.json"), json, &BTreeSet::new(), None)
+            .iter()
+            .map(|d| d.column)
+            .collect();
+        assert_eq!(columns.len(), 2);
+        assert!(columns[0] < columns[1], "{:?}", columns);
+    }
+}
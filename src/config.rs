@@ -1,22 +1,96 @@
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::ffi::OsStr;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
-    #[serde(rename = "command")]
-    pub _command: String,
-    #[serde(default, rename = "description")]
-    pub _description: String,
+    /// File name, without `.json`; set when loaded
+    #[serde(skip)]
+    pub name: String,
+    /// The command the wizard builds on, e.g. `docker run`
+    pub command: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
     pub steps: Vec<Step>,
     #[serde(default)]
     pub presets: Vec<Preset>,
     #[serde(default)]
-    pub placeholder_options: HashMap<String, String>,
+    pub placeholder_options: HashMap<String, PlaceholderOption>,
+    /// Always run through `sh -c`, for configs built around shell features
+    #[serde(default)]
+    pub shell: bool,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// How to fetch the values offered for a placeholder. Configs may give just
+/// the command string, or an object with extra settings.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(from = "PlaceholderOptionDef")]
+pub struct PlaceholderOption {
+    pub command: String,
+    /// Seconds to reuse fetched values; 0 disables caching
+    pub cache_ttl: Option<u64>,
+    /// Keep fetched values on disk between runs of `i`
+    pub cache_persist: bool,
+    /// Seconds to wait for the command before giving up
+    pub timeout: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PlaceholderOptionDef {
+    Command(String),
+    Detailed {
+        command: String,
+        #[serde(default)]
+        cache_ttl: Option<u64>,
+        #[serde(default)]
+        cache_persist: bool,
+        #[serde(default)]
+        timeout: Option<u64>,
+    },
+}
+
+impl From<PlaceholderOptionDef> for PlaceholderOption {
+    fn from(def: PlaceholderOptionDef) -> Self {
+        match def {
+            PlaceholderOptionDef::Command(command) => command.into(),
+            PlaceholderOptionDef::Detailed {
+                command,
+                cache_ttl,
+                cache_persist,
+                timeout,
+            } => PlaceholderOption {
+                command,
+                cache_ttl,
+                cache_persist,
+                timeout,
+            },
+        }
+    }
+}
+
+impl From<String> for PlaceholderOption {
+    fn from(command: String) -> Self {
+        PlaceholderOption {
+            command,
+            cache_ttl: None,
+            cache_persist: false,
+            timeout: None,
+        }
+    }
+}
+
+impl From<&str> for PlaceholderOption {
+    fn from(command: &str) -> Self {
+        command.to_string().into()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct Preset {
     pub label: String,
     pub flags: String,
@@ -38,6 +112,8 @@ pub struct Step {
     pub when: Option<HashMap<String, String>>,
     #[serde(default)]
     pub placeholder: Option<String>,
+    #[serde(default)]
+    pub quote: Quote,
 }
 
 #[derive(Debug, Clone, Deserialize, PartialEq)]
@@ -49,13 +125,102 @@ pub enum StepType {
     Multi,
 }
 
+/// How a text step's value is quoted in the command
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Quote {
+    /// Quote only when the shell would otherwise interpret the value
+    #[default]
+    Auto,
+    /// Insert as typed, for values that are meant to use shell syntax
+    None,
+    Single,
+    Double,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct StepOption {
     pub label: String,
     #[serde(default)]
     pub flag: Option<String>,
     #[serde(default)]
-    pub chain: Option<String>,
+    pub chain: Option<Chain>,
+}
+
+/// Where a chain option leads. Configs may give just the config's file name,
+/// or an object with the command it stands for when that isn't the config's
+/// own `command`, and what to pass on to it.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(from = "ChainDef")]
+pub struct Chain {
+    /// File name of the config, without `.json`, e.g. `docker-run`
+    pub config: String,
+    /// The command words, e.g. `redis-cli`
+    pub command: Option<String>,
+    /// Ids of steps whose flags go along, placed after this config's
+    /// command, as in `kubectl -n prod logs`
+    pub flags: Vec<String>,
+    /// Answers for the chained config's steps, by id. `{{step_id}}` stands
+    /// for this config's answer to that step.
+    pub answers: HashMap<String, String>,
+    /// Label of a preset to highlight in the chained config's menu
+    pub preset: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChainDef {
+    Config(String),
+    Detailed {
+        config: String,
+        #[serde(default)]
+        command: Option<String>,
+        #[serde(default)]
+        flags: Vec<String>,
+        #[serde(default)]
+        answers: HashMap<String, String>,
+        #[serde(default)]
+        preset: Option<String>,
+    },
+}
+
+impl From<ChainDef> for Chain {
+    fn from(def: ChainDef) -> Self {
+        match def {
+            ChainDef::Config(config) => config.into(),
+            ChainDef::Detailed {
+                config,
+                command,
+                flags,
+                answers,
+                preset,
+            } => Chain {
+                config,
+                command,
+                flags,
+                answers,
+                preset,
+            },
+        }
+    }
+}
+
+impl From<String> for Chain {
+    fn from(config: String) -> Self {
+        Chain {
+            config,
+            command: None,
+            flags: Vec::new(),
+            answers: HashMap::new(),
+            preset: None,
+        }
+    }
+}
+
+impl From<&str> for Chain {
+    fn from(config: &str) -> Self {
+        config.to_string().into()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -67,53 +232,591 @@ pub enum Answer {
 }
 
 impl Config {
-    pub fn load(command: &[String]) -> Result<Config, ConfigError> {
-        let config_name = command.join("-");
+    /// Load the config named `name`, e.g. `docker-run`
+    pub fn load(name: &str) -> Result<Config, ConfigError> {
+        Self::load_with_origins(name).map(|(config, _)| config)
+    }
 
-        // Try loading from each location in order
-        let paths = config_paths(&config_name);
+    /// Load a config with a note of which file each part of it came from
+    pub fn load_with_origins(name: &str) -> Result<(Config, Origins), ConfigError> {
+        let (mut config, origins) = merge_layers(&config_layers(name))?
+            .ok_or_else(|| ConfigError::NotFound(name.to_string(), config_paths(name)))?;
+        config.name = name.to_string();
+        Ok((config, origins))
+    }
 
-        for path in &paths {
-            if path.exists() {
-                let content = fs::read_to_string(path)
-                    .map_err(|e| ConfigError::ReadError(path.clone(), e.to_string()))?;
-                let config: Config = serde_json::from_str(&content)
-                    .map_err(|e| ConfigError::ParseError(path.clone(), e.to_string()))?;
-                return Ok(config);
+    /// The words of `command`, which the wizard's flags are added to
+    pub fn command_words(&self) -> Vec<String> {
+        self.command.split_whitespace().map(String::from).collect()
+    }
+}
+
+impl Chain {
+    /// The command words the chain gives, if any
+    pub fn command_words(&self) -> Option<Vec<String>> {
+        let command = self.command.as_ref()?;
+        Some(command.split_whitespace().map(String::from).collect())
+    }
+}
+
+/// Where a config file was found
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Bundled,
+    /// A directory in `$I_CONFIG_PATH`
+    ConfigPath,
+    Project,
+    User,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Source::Bundled => "bundled",
+            Source::ConfigPath => "I_CONFIG_PATH",
+            Source::Project => "project",
+            Source::User => "user",
+        })
+    }
+}
+
+/// Which file each part of a merged config came from
+#[derive(Debug, Clone, Default)]
+pub struct Origins {
+    /// The files merged, lowest priority first
+    pub files: Vec<(Source, PathBuf)>,
+    pub command: PathBuf,
+    /// One per step of the config, in order
+    pub steps: Vec<PathBuf>,
+    /// Step ids removed with `"disabled": true`, and the file that did it
+    pub disabled: Vec<(String, PathBuf)>,
+    /// One per preset of the config, in order
+    pub presets: Vec<PathBuf>,
+    pub placeholder_options: HashMap<String, PathBuf>,
+}
+
+impl Origins {
+    fn source(&self, path: &Path) -> String {
+        self.files
+            .iter()
+            .find(|(_, file)| file == path)
+            .map_or_else(
+                || path.display().to_string(),
+                |(source, _)| source.to_string(),
+            )
+    }
+}
+
+/// One config file, before merging. Everything is optional, so a file can
+/// add to or change a config from a lower layer without repeating it.
+#[derive(Deserialize)]
+struct Layer {
+    command: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    steps: Vec<LayerStep>,
+    #[serde(default)]
+    presets: Vec<Preset>,
+    #[serde(default)]
+    placeholder_options: HashMap<String, PlaceholderOption>,
+    shell: Option<bool>,
+}
+
+/// A step in a layer, or `{ "id": "...", "disabled": true }` to remove the
+/// step with that id from lower layers
+enum LayerStep {
+    Step(Step),
+    Disabled(String),
+}
+
+impl<'de> Deserialize<'de> for LayerStep {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let value = serde_json::Value::deserialize(deserializer)?;
+        if value.get("disabled") == Some(&serde_json::Value::Bool(true)) {
+            let id = value
+                .get("id")
+                .and_then(|id| id.as_str())
+                .ok_or_else(|| D::Error::missing_field("id"))?;
+            return Ok(LayerStep::Disabled(id.to_string()));
+        }
+        serde_json::from_value(value)
+            .map(LayerStep::Step)
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Read `text` the way a config file is read when merging, for `--check`
+pub fn parse_layer(text: &str) -> Result<(), serde_json::Error> {
+    serde_json::from_str::<Layer>(text).map(|_| ())
+}
+
+/// Merge the config files among `layers` that exist, lowest priority first.
+/// Later files override the command, description and `shell`, replace steps
+/// with the same id (or remove them), add new steps at the end, and add
+/// presets and placeholder options.
+pub fn merge_layers(
+    layers: &[(Source, PathBuf)],
+) -> Result<Option<(Config, Origins)>, ConfigError> {
+    let mut config = Config {
+        name: String::new(),
+        command: String::new(),
+        description: String::new(),
+        steps: Vec::new(),
+        presets: Vec::new(),
+        placeholder_options: HashMap::new(),
+        shell: false,
+    };
+    let mut origins = Origins::default();
+
+    for (source, path) in layers.iter().filter(|(_, path)| layer_exists(path)) {
+        let content = read_layer(path)?;
+        let layer: Layer = serde_json::from_str(&content)
+            .map_err(|e| ConfigError::ParseError(path.clone(), e.to_string()))?;
+        origins.files.push((*source, path.clone()));
+
+        if let Some(command) = layer.command {
+            config.command = command;
+            origins.command = path.clone();
+        }
+        if let Some(description) = layer.description {
+            config.description = description;
+        }
+        if let Some(shell) = layer.shell {
+            config.shell = shell;
+        }
+        for step in layer.steps {
+            match step {
+                LayerStep::Step(step) => match config.steps.iter().position(|s| s.id == step.id) {
+                    Some(i) => {
+                        config.steps[i] = step;
+                        origins.steps[i] = path.clone();
+                    }
+                    None => {
+                        config.steps.push(step);
+                        origins.steps.push(path.clone());
+                    }
+                },
+                LayerStep::Disabled(id) => {
+                    if let Some(i) = config.steps.iter().position(|s| s.id == id) {
+                        config.steps.remove(i);
+                        origins.steps.remove(i);
+                    }
+                    origins.disabled.push((id, path.clone()));
+                }
+            }
+        }
+        for preset in layer.presets {
+            // A project checkout of the bundled configs repeats their presets
+            if !config.presets.contains(&preset) {
+                config.presets.push(preset);
+                origins.presets.push(path.clone());
             }
         }
+        for (name, option) in layer.placeholder_options {
+            origins
+                .placeholder_options
+                .insert(name.clone(), path.clone());
+            config.placeholder_options.insert(name, option);
+        }
+    }
 
-        Err(ConfigError::NotFound(config_name, paths))
+    let Some((_, last)) = origins.files.last() else {
+        return Ok(None);
+    };
+    if config.command.is_empty() {
+        return Err(ConfigError::ParseError(
+            last.clone(),
+            "missing field `command` (in this file or one it extends)".to_string(),
+        ));
     }
+    Ok(Some((config, origins)))
 }
 
-fn config_paths(name: &str) -> Vec<PathBuf> {
-    let mut paths = Vec::new();
+/// A readable account of a merged config: the files it came from, and for
+/// every step, preset and placeholder option, which of them set it
+pub fn explain(config: &Config, origins: &Origins) -> String {
+    let mut out = String::new();
+    let mut line = |text: String| {
+        out.push_str(&text);
+        out.push('\n');
+    };
+
+    line(format!(
+        "{}  ({})",
+        config.command,
+        origins.source(&origins.command)
+    ));
+    if !config.description.is_empty() {
+        line(config.description.clone());
+    }
+    line(String::new());
+    line("Files, lowest priority first:".to_string());
+    for (source, path) in &origins.files {
+        line(format!("  {:<8} {}", source.to_string(), path.display()));
+    }
+
+    line(String::new());
+    line("Steps:".to_string());
+    for (step, path) in config.steps.iter().zip(&origins.steps) {
+        line(format!(
+            "  {:<8} {:<16} {}",
+            origins.source(path),
+            step.id,
+            step.prompt
+        ));
+    }
+    for (id, path) in &origins.disabled {
+        line(format!(
+            "  {:<8} {:<16} (disabled)",
+            origins.source(path),
+            id
+        ));
+    }
+
+    if !config.presets.is_empty() {
+        line(String::new());
+        line("Presets:".to_string());
+        for (preset, path) in config.presets.iter().zip(&origins.presets) {
+            line(format!(
+                "  {:<8} {}  ({})",
+                origins.source(path),
+                preset.label,
+                preset.flags
+            ));
+        }
+    }
+
+    if !config.placeholder_options.is_empty() {
+        line(String::new());
+        line("Placeholder options:".to_string());
+        let mut names: Vec<&String> = config.placeholder_options.keys().collect();
+        names.sort();
+        for name in names {
+            let source = origins
+                .placeholder_options
+                .get(name)
+                .map(|path| origins.source(path))
+                .unwrap_or_default();
+            line(format!(
+                "  {:<8} {:<16} {}",
+                source, name, config.placeholder_options[name].command
+            ));
+        }
+    }
+
+    out
+}
+
+/// Add `preset` to the user's overlay for a config, e.g.
+/// `~/.config/i/docker-run.json`, creating it if needed. Returns its path.
+pub fn save_user_preset(name: &str, preset: &Preset) -> Result<PathBuf, ConfigError> {
+    let path = user_config_path(name).ok_or_else(|| {
+        ConfigError::WriteError(PathBuf::from(name), "no config directory".to_string())
+    })?;
+    add_preset(&path, preset)?;
+    Ok(path)
+}
+
+/// Append `preset` to the `presets` of the config file at `path`, leaving
+/// the rest of the file as it is. A new file only has the preset; the
+/// command comes from the layers below it.
+pub fn add_preset(path: &Path, preset: &Preset) -> Result<(), ConfigError> {
+    let mut value = if path.exists() {
+        let content = fs::read_to_string(path)
+            .map_err(|e| ConfigError::ReadError(path.to_path_buf(), e.to_string()))?;
+        serde_json::from_str(&content)
+            .map_err(|e| ConfigError::ParseError(path.to_path_buf(), e.to_string()))?
+    } else {
+        serde_json::json!({})
+    };
+    let Some(object) = value.as_object_mut() else {
+        return Err(ConfigError::ParseError(
+            path.to_path_buf(),
+            "expected a JSON object".to_string(),
+        ));
+    };
+    let presets = object
+        .entry("presets")
+        .or_insert_with(|| serde_json::Value::Array(Vec::new()));
+    let Some(presets) = presets.as_array_mut() else {
+        return Err(ConfigError::ParseError(
+            path.to_path_buf(),
+            "\"presets\" is not an array".to_string(),
+        ));
+    };
+    presets.push(serde_json::to_value(preset).expect("presets serialize"));
+
+    let write_error =
+        |e: std::io::Error| ConfigError::WriteError(path.to_path_buf(), e.to_string());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(write_error)?;
+    }
+    let json = serde_json::to_string_pretty(&value).expect("JSON values serialize");
+    fs::write(path, json + "\n").map_err(write_error)
+}
+
+/// The longest leading run of `words` that has a config, so a command line
+/// like `docker run -d nginx` finds `docker-run`
+pub fn longest_config_prefix(words: &[String]) -> Option<&[String]> {
+    (1..=words.len())
+        .rev()
+        .map(|len| &words[..len])
+        .find(|prefix| {
+            config_layers(&prefix.join("-"))
+                .iter()
+                .any(|(_, path)| layer_exists(path))
+        })
+}
+
+/// A config as `--list` and the picker show it
+#[derive(Debug, Clone)]
+pub struct ConfigSummary {
+    pub name: String,
+    pub description: String,
+    /// The files it's merged from, lowest priority first
+    pub files: Vec<(Source, PathBuf)>,
+}
+
+/// The names of every config there is
+pub fn config_names() -> BTreeSet<String> {
+    let mut names: BTreeSet<String> = BUNDLED.iter().map(|(name, _)| name.to_string()).collect();
+    for path in config_files() {
+        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+            names.insert(name.to_string());
+        }
+    }
+    names
+}
+
+/// The config files on disk, in the order of `config_dirs`; bundled
+/// configs aren't included
+pub fn config_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for (source, dir) in config_dirs() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        paths.sort();
+        for path in paths {
+            // settings.json shares the user's directory
+            let is_settings =
+                source == Source::User && path.file_stem() == Some(OsStr::new("settings"));
+            if path.extension().is_some_and(|ext| ext == "json") && !is_settings {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
 
-    // 1. Project-local: ./.i/<name>.json
-    paths.push(PathBuf::from(format!(".i/{}.json", name)));
+/// Every config there is, by name
+pub fn list_configs() -> Vec<ConfigSummary> {
+    config_names()
+        .into_iter()
+        .map(|name| {
+            let layers = config_layers(&name);
+            // A broken config is still listed, so it can be found and fixed
+            let (description, files) = match merge_layers(&layers) {
+                Ok(Some((config, origins))) => (config.description, origins.files),
+                _ => (
+                    String::new(),
+                    layers
+                        .into_iter()
+                        .filter(|(_, path)| layer_exists(path))
+                        .collect(),
+                ),
+            };
+            ConfigSummary {
+                name,
+                description,
+                files,
+            }
+        })
+        .collect()
+}
+
+/// Every config that loads, by name
+pub fn load_all() -> Vec<Config> {
+    config_names()
+        .iter()
+        .filter_map(|name| Config::load(name).ok())
+        .collect()
+}
+
+/// A config for picking one of `configs`: a single choice chaining to the
+/// chosen config, searchable by name and description
+pub fn picker_config(configs: &[ConfigSummary]) -> Config {
+    let width = configs.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    let options = configs
+        .iter()
+        .map(|c| StepOption {
+            label: format!("{:<width$}  {}", c.name, c.description)
+                .trim_end()
+                .to_string(),
+            flag: None,
+            chain: Some(Chain::from(c.name.as_str())),
+        })
+        .collect();
+    Config {
+        name: "i".to_string(),
+        command: "i".to_string(),
+        description: String::new(),
+        steps: vec![Step {
+            id: "config".to_string(),
+            prompt: "Which command? (/ to search)".to_string(),
+            step_type: StepType::Choice,
+            options,
+            flag: None,
+            default: None,
+            when: None,
+            placeholder: None,
+            quote: Quote::Auto,
+        }],
+        presets: Vec::new(),
+        placeholder_options: HashMap::new(),
+        shell: false,
+    }
+}
+
+// The configs in the repo's `.i/`, as (name, JSON) pairs, compiled in by
+// build.rs
+include!(concat!(env!("OUT_DIR"), "/bundled.rs"));
+
+/// Where bundled configs appear to live, in messages and `--show-config`
+const BUNDLED_DIR: &str = "(bundled)";
+
+fn bundled(name: &str) -> Option<&'static str> {
+    BUNDLED
+        .iter()
+        .find(|(bundled_name, _)| *bundled_name == name)
+        .map(|(_, json)| *json)
+}
+
+/// The name of the bundled config at a path under `BUNDLED_DIR`
+fn bundled_name(path: &Path) -> Option<&str> {
+    path.strip_prefix(BUNDLED_DIR).ok()?.file_stem()?.to_str()
+}
+
+fn layer_exists(path: &Path) -> bool {
+    if path.starts_with(BUNDLED_DIR) {
+        return bundled_name(path).and_then(bundled).is_some();
+    }
+    path.exists()
+}
 
-    // 2. User config: ~/.config/i/<name>.json
-    if let Some(config_dir) = dirs::config_dir() {
-        paths.push(config_dir.join("i").join(format!("{}.json", name)));
+fn read_layer(path: &Path) -> Result<String, ConfigError> {
+    if let Some(json) = bundled_name(path).and_then(bundled) {
+        return Ok(json.to_string());
     }
+    fs::read_to_string(path).map_err(|e| ConfigError::ReadError(path.to_path_buf(), e.to_string()))
+}
+
+/// The config a file adds to, if it's one of the layers of the config it's
+/// named for: every layer below it, merged. `None` for any other file.
+pub fn load_below(path: &Path) -> Option<Config> {
+    let name = path.file_stem()?.to_str()?;
+    let path = fs::canonicalize(path).ok()?;
+    let layers = config_layers(name);
+    let at = layers
+        .iter()
+        .position(|(_, layer)| fs::canonicalize(layer).is_ok_and(|layer| layer == path))?;
+    let (mut config, _) = merge_layers(&layers[..at]).ok()??;
+    config.name = name.to_string();
+    Some(config)
+}
+
+/// Where a config's files may be, lowest priority first: bundled, then
+/// `$I_CONFIG_PATH`, project-local (farthest first), then the user's own
+fn config_layers(name: &str) -> Vec<(Source, PathBuf)> {
+    let file = format!("{}.json", name);
+    let mut layers = vec![(Source::Bundled, Path::new(BUNDLED_DIR).join(&file))];
+    layers.extend(
+        config_dirs()
+            .into_iter()
+            .map(|(source, dir)| (source, dir.join(&file))),
+    );
+    layers
+}
+
+/// Directories to read configs from, lowest priority first
+fn config_dirs() -> Vec<(Source, PathBuf)> {
+    // Extra directories: $I_CONFIG_PATH
+    let mut dirs: Vec<(Source, PathBuf)> = env::var_os("I_CONFIG_PATH")
+        .map(|paths| config_path_dirs(&paths))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| (Source::ConfigPath, dir))
+        .collect();
+
+    // Project-local: .i/<name>.json here and in parent directories
+    let project_dirs = match env::current_dir() {
+        Ok(cwd) => project_dirs(&cwd),
+        Err(_) => vec![PathBuf::from(".i")],
+    };
+    dirs.extend(project_dirs.into_iter().map(|dir| (Source::Project, dir)));
 
-    // 3. Bundled: could be embedded, but for now use a data dir
-    // For development, check relative to executable
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(exe_dir) = exe_path.parent() {
-            paths.push(exe_dir.join("i").join(format!("{}.json", name)));
+    // User config: ~/.config/i/<name>.json
+    if let Some(dir) = user_config_dir() {
+        dirs.push((Source::User, dir));
+    }
+
+    dirs
+}
+
+/// The directories in an `I_CONFIG_PATH` value, lowest priority first.
+/// Like `PATH`, earlier entries win.
+fn config_path_dirs(paths: &OsStr) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = env::split_paths(paths)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect();
+    dirs.reverse();
+    dirs
+}
+
+/// The `.i` directories in `start` and its parents, farthest first, the way
+/// git looks for `.git`. The search stops at the root of a git repo.
+fn project_dirs(start: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for dir in start.ancestors() {
+        let config_dir = dir.join(".i");
+        if config_dir.is_dir() {
+            dirs.push(config_dir);
+        }
+        if dir.join(".git").exists() {
+            break;
         }
     }
+    dirs.reverse();
+    dirs
+}
 
+/// Where a config's file can be created: the current directory's `.i`, or
+/// the user's config directory
+fn config_paths(name: &str) -> Vec<PathBuf> {
+    let file = format!("{}.json", name);
+    let mut paths = vec![PathBuf::from(".i").join(&file)];
+    paths.extend(user_config_dir().map(|dir| dir.join(&file)));
     paths
 }
 
+fn user_config_dir() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("i"))
+}
+
+/// Where the user's own config (or overlay) for `name` lives
+fn user_config_path(name: &str) -> Option<PathBuf> {
+    Some(user_config_dir()?.join(format!("{}.json", name)))
+}
+
 #[derive(Debug)]
 pub enum ConfigError {
     NotFound(String, Vec<PathBuf>),
     ReadError(PathBuf, String),
     ParseError(PathBuf, String),
+    WriteError(PathBuf, String),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -135,6 +838,9 @@ impl std::fmt::Display for ConfigError {
             ConfigError::ParseError(path, err) => {
                 write!(f, "Failed to parse {}: {}", path.display(), err)
             }
+            ConfigError::WriteError(path, err) => {
+                write!(f, "Failed to write {}: {}", path.display(), err)
+            }
         }
     }
 }
@@ -170,8 +876,8 @@ mod tests {
         }"#;
 
         let config: Config = serde_json::from_str(json).unwrap();
-        assert_eq!(config._command, "ls");
-        assert_eq!(config._description, "List files");
+        assert_eq!(config.command, "ls");
+        assert_eq!(config.description, "List files");
         assert_eq!(config.steps.len(), 2);
         assert_eq!(config.steps[0].step_type, StepType::Choice);
         assert_eq!(config.steps[1].step_type, StepType::Toggle);
@@ -211,6 +917,49 @@ mod tests {
         assert!(config.placeholder_options.contains_key("<container>"));
     }
 
+    #[test]
+    fn test_parse_step_quote() {
+        let json = r#"{
+            "command": "tar",
+            "steps": [
+                { "id": "files", "prompt": "Files", "type": "text", "quote": "none" },
+                { "id": "name", "prompt": "Name", "type": "text" }
+            ]
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.steps[0].quote, Quote::None);
+        assert_eq!(config.steps[1].quote, Quote::Auto);
+    }
+
+    #[test]
+    fn test_parse_placeholder_options_with_cache_settings() {
+        let json = r#"{
+            "command": "aws ec2",
+            "steps": [],
+            "placeholder_options": {
+                "<instance>": {
+                    "command": "aws ec2 describe-instances",
+                    "cache_ttl": 30,
+                    "cache_persist": true,
+                    "timeout": 20
+                },
+                "<region>": "aws ec2 describe-regions"
+            }
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        let instance = &config.placeholder_options["<instance>"];
+        assert_eq!(instance.command, "aws ec2 describe-instances");
+        assert_eq!(instance.cache_ttl, Some(30));
+        assert!(instance.cache_persist);
+        assert_eq!(instance.timeout, Some(20));
+        assert_eq!(
+            config.placeholder_options["<region>"],
+            PlaceholderOption::from("aws ec2 describe-regions")
+        );
+    }
+
     #[test]
     fn test_parse_text_step() {
         let json = r#"{
@@ -269,18 +1018,27 @@ mod tests {
                     "type": "choice",
                     "options": [
                         { "label": "Run container", "chain": "docker-run" },
-                        { "label": "List containers", "flag": "ps" }
+                        { "label": "List containers", "flag": "ps" },
+                        {
+                            "label": "Compose up",
+                            "chain": { "config": "docker-compose-up", "command": "docker-compose up" }
+                        }
                     ]
                 }
             ]
         }"#;
 
         let config: Config = serde_json::from_str(json).unwrap();
+        let options = &config.steps[0].options;
+        assert_eq!(options[0].chain, Some("docker-run".into()));
+        assert_eq!(options[0].chain.as_ref().unwrap().command_words(), None);
+        assert_eq!(options[1].chain, None);
+        let chain = options[2].chain.as_ref().unwrap();
+        assert_eq!(chain.config, "docker-compose-up");
         assert_eq!(
-            config.steps[0].options[0].chain,
-            Some("docker-run".to_string())
+            chain.command_words(),
+            Some(vec!["docker-compose".to_string(), "up".to_string()])
         );
-        assert_eq!(config.steps[0].options[1].chain, None);
     }
 
     #[test]
@@ -402,6 +1160,181 @@ mod tests {
         assert!(paths.iter().any(|p| p.ends_with(".i/docker-run.json")));
     }
 
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("i-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_layers(dir: &Path, files: &[(Source, &str, &str)]) -> Vec<(Source, PathBuf)> {
+        files
+            .iter()
+            .map(|(source, name, json)| {
+                let path = dir.join(name);
+                fs::write(&path, json).unwrap();
+                (*source, path)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_layers() {
+        let dir = temp_dir("config-layers");
+        let layers = write_layers(
+            &dir,
+            &[
+                (
+                    Source::Bundled,
+                    "bundled.json",
+                    r#"{
+                        "command": "ls",
+                        "description": "List files",
+                        "steps": [
+                            { "id": "format", "prompt": "Format?", "type": "choice",
+                              "options": [{ "label": "Long", "flag": "-l" }] },
+                            { "id": "hidden", "prompt": "Hidden?", "type": "toggle", "flag": "-a" },
+                            { "id": "sort", "prompt": "Sort?", "type": "toggle", "flag": "-t" }
+                        ],
+                        "presets": [{ "label": "All", "flags": "-a" }],
+                        "placeholder_options": { "<dir>": "ls -d */" }
+                    }"#,
+                ),
+                (
+                    Source::Project,
+                    "project.json",
+                    r#"{
+                        "steps": [
+                            { "id": "hidden", "prompt": "Dotfiles too?", "type": "toggle", "flag": "-A" },
+                            { "id": "path", "prompt": "Path?", "type": "text" }
+                        ],
+                        "placeholder_options": { "<dir>": "find . -type d" }
+                    }"#,
+                ),
+                (
+                    Source::User,
+                    "user.json",
+                    r#"{
+                        "command": "ls",
+                        "steps": [{ "id": "sort", "disabled": true }],
+                        "presets": [{ "label": "Mine", "flags": "-la" }]
+                    }"#,
+                ),
+            ],
+        );
+
+        let (config, origins) = merge_layers(&layers).unwrap().unwrap();
+        let ids: Vec<&str> = config.steps.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, ["format", "hidden", "path"]);
+        assert_eq!(config.steps[1].prompt, "Dotfiles too?");
+        assert_eq!(config.description, "List files");
+        let labels: Vec<&str> = config.presets.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, ["All", "Mine"]);
+        assert_eq!(
+            config.placeholder_options["<dir>"].command,
+            "find . -type d"
+        );
+
+        assert_eq!(origins.steps[0], layers[0].1);
+        assert_eq!(origins.steps[1], layers[1].1);
+        assert_eq!(
+            origins.disabled,
+            [("sort".to_string(), layers[2].1.clone())]
+        );
+        assert_eq!(origins.presets[1], layers[2].1);
+        assert_eq!(origins.command, layers[2].1);
+
+        let report = explain(&config, &origins);
+        assert!(report.contains("Dotfiles too?"), "{}", report);
+        assert!(report.contains("(disabled)"), "{}", report);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_merge_layers_missing_files() {
+        let dir = temp_dir("config-layers-missing");
+        let missing = (Source::Project, dir.join("missing.json"));
+        assert!(merge_layers(std::slice::from_ref(&missing))
+            .unwrap()
+            .is_none());
+
+        // An overlay on its own still needs a command
+        let layers = write_layers(&dir, &[(Source::User, "user.json", r#"{ "presets": [] }"#)]);
+        assert!(matches!(
+            merge_layers(&[missing, layers[0].clone()]),
+            Err(ConfigError::ParseError(..))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_add_preset_creates_and_appends() {
+        let dir = temp_dir("add-preset");
+        let layers = write_layers(
+            &dir,
+            &[(
+                Source::Project,
+                "base.json",
+                r#"{ "command": "docker run" }"#,
+            )],
+        );
+        let path = dir.join("i").join("docker-run.json");
+        let preset = |label: &str, flags: &str| Preset {
+            label: label.to_string(),
+            flags: flags.to_string(),
+        };
+        add_preset(&path, &preset("Web", "-d -p 80:80 nginx")).unwrap();
+        add_preset(&path, &preset("Shell", "-it <image> sh")).unwrap();
+
+        // The overlay only adds presets
+        assert!(!fs::read_to_string(&path).unwrap().contains("command"));
+        let (config, _) = merge_layers(&[layers[0].clone(), (Source::User, path.clone())])
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.command, "docker run");
+        assert_eq!(config.presets.len(), 2);
+        assert_eq!(config.presets[1].flags, "-it <image> sh");
+
+        fs::write(&path, r#"{ "command": "x", "presets": {} }"#).unwrap();
+        assert!(matches!(
+            add_preset(&path, &preset("A", "a")),
+            Err(ConfigError::ParseError(..))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_project_dirs_walk_up_to_git_root() {
+        let dir = temp_dir("project-dirs");
+        let repo = dir.join("repo");
+        let nested = repo.join("src").join("deep");
+        fs::create_dir_all(&nested).unwrap();
+        for config_dir in [dir.join(".i"), repo.join(".i"), repo.join("src").join(".i")] {
+            fs::create_dir_all(config_dir).unwrap();
+        }
+
+        // Outside a repo, every parent counts
+        assert_eq!(
+            project_dirs(&nested),
+            [dir.join(".i"), repo.join(".i"), repo.join("src").join(".i")]
+        );
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        assert_eq!(
+            project_dirs(&nested),
+            [repo.join(".i"), repo.join("src").join(".i")]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_config_path_dirs_earlier_entries_win() {
+        let paths = env::join_paths(["/team/configs", "", "/shared"]).unwrap();
+        assert_eq!(
+            config_path_dirs(&paths),
+            [PathBuf::from("/shared"), PathBuf::from("/team/configs")]
+        );
+    }
+
     #[test]
     fn test_answer_variants() {
         // Just verify we can create each Answer variant
@@ -436,8 +1369,8 @@ mod tests {
         }"#;
 
         let config: Config = serde_json::from_str(json).unwrap();
-        assert_eq!(config._command, "test");
-        assert_eq!(config._description, ""); // default
+        assert_eq!(config.command, "test");
+        assert_eq!(config.description, ""); // default
         assert!(config.steps.is_empty());
         assert!(config.presets.is_empty());
         assert!(config.placeholder_options.is_empty());
@@ -457,12 +1390,74 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_bundled_configs_are_compiled_in() {
+        let count = fs::read_dir(".i")
+            .unwrap()
+            .filter(|entry| {
+                let path = entry.as_ref().unwrap().path();
+                path.extension().is_some_and(|ext| ext == "json")
+            })
+            .count();
+        assert_eq!(BUNDLED.len(), count);
+
+        let path = Path::new(BUNDLED_DIR).join("docker-run.json");
+        assert!(layer_exists(&path));
+        assert!(!layer_exists(&Path::new(BUNDLED_DIR).join("nope.json")));
+        let json = read_layer(&path).unwrap();
+        assert_eq!(json, fs::read_to_string(".i/docker-run.json").unwrap());
+    }
+
+    #[test]
+    fn test_list_configs_includes_bundled() {
+        let configs = list_configs();
+        let ls = configs.iter().find(|c| c.name == "ls").unwrap();
+        assert_eq!(ls.description, "List directory contents");
+        assert_eq!(ls.files[0].0, Source::Bundled);
+        assert!(configs.windows(2).all(|w| w[0].name < w[1].name));
+    }
+
+    #[test]
+    fn test_picker_config_chains_to_each_config() {
+        let summary = |name: &str, description: &str| ConfigSummary {
+            name: name.to_string(),
+            description: description.to_string(),
+            files: Vec::new(),
+        };
+        let config = picker_config(&[summary("ls", "List files"), summary("docker-run", "")]);
+        let options = &config.steps[0].options;
+        assert_eq!(options[0].label, "ls          List files");
+        assert_eq!(options[0].chain, Some(Chain::from("ls")));
+        assert_eq!(options[1].label, "docker-run");
+        assert_eq!(options[1].chain, Some(Chain::from("docker-run")));
+    }
+
+    #[test]
+    fn test_load_takes_command_from_config() {
+        let config = Config::load("ffmpeg-audio").unwrap();
+        assert_eq!(config.name, "ffmpeg-audio");
+        assert_eq!(config.command_words(), ["ffmpeg"]);
+    }
+
+    #[test]
+    fn test_bundled_and_project_presets_merge_once() {
+        let layers = [
+            (Source::Bundled, Path::new(BUNDLED_DIR).join("ls.json")),
+            (Source::Project, PathBuf::from(".i/ls.json")),
+        ];
+        let (config, origins) = merge_layers(&layers).unwrap().unwrap();
+        let (bundled, _) = merge_layers(&layers[..1]).unwrap().unwrap();
+        assert_eq!(config.presets, bundled.presets);
+        assert!(origins.presets.iter().all(|path| *path == layers[0].1));
+    }
+
     #[test]
     fn test_all_bundled_configs_are_valid() {
         let config_dir = std::path::Path::new(".i");
         assert!(config_dir.exists(), ".i directory should exist");
 
         let mut errors = Vec::new();
+        let mut paths = Vec::new();
 
         for entry in std::fs::read_dir(config_dir).unwrap() {
             let entry = entry.unwrap();
@@ -473,6 +1468,21 @@ mod tests {
                 if let Err(e) = serde_json::from_str::<Config>(&content) {
                     errors.push(format!("{}: {}", path.display(), e));
                 }
+                paths.push(path);
+            }
+        }
+
+        // The same checks as `i --check`, though against these files alone,
+        // whatever else is installed
+        paths.sort();
+        let names = paths
+            .iter()
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        for path in &paths {
+            let content = std::fs::read_to_string(path).unwrap();
+            for diagnostic in crate::check::check(path, &content, &names, None) {
+                errors.push(diagnostic.to_string());
             }
         }
 
@@ -0,0 +1,275 @@
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a placeholder fetch may run when a config doesn't say
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, PartialEq)]
+pub enum FetchOutcome {
+    Values(Vec<(String, String)>),
+    Failed { code: Option<i32>, stderr: String },
+    TimedOut(Duration),
+    Cancelled,
+    SpawnError(String),
+}
+
+impl FetchOutcome {
+    /// Lines to show in place of the option list when the fetch didn't work.
+    /// The first line is a summary, the rest is the command's stderr.
+    pub fn error_lines(&self) -> Vec<String> {
+        match self {
+            FetchOutcome::Values(_) => Vec::new(),
+            FetchOutcome::Failed { code, stderr } => {
+                let summary = match code {
+                    Some(code) => format!("Fetch failed (exit code {})", code),
+                    None => "Fetch failed (killed by signal)".to_string(),
+                };
+                std::iter::once(summary)
+                    .chain(stderr.lines().map(str::to_string))
+                    .collect()
+            }
+            FetchOutcome::TimedOut(after) => {
+                vec![format!("Fetch timed out after {}s", after.as_secs_f32())]
+            }
+            FetchOutcome::Cancelled => vec!["Fetch cancelled".to_string()],
+            FetchOutcome::SpawnError(err) => vec![format!("Fetch failed: {}", err)],
+        }
+    }
+}
+
+/// A fetch command running on a background thread
+pub struct PendingFetch {
+    pub command: String,
+    started: Instant,
+    receiver: Receiver<FetchOutcome>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl PendingFetch {
+    pub fn spawn(command: String, timeout: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread_command = command.clone();
+        let thread_cancel = Arc::clone(&cancel);
+        thread::spawn(move || {
+            let outcome = run(&thread_command, timeout, &thread_cancel);
+            // The receiver is gone if the wizard moved on; nothing to report to
+            let _ = sender.send(outcome);
+        });
+
+        Self {
+            command,
+            started: Instant::now(),
+            receiver,
+            cancel,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// The outcome, if the command has finished
+    pub fn try_finish(&self) -> Option<FetchOutcome> {
+        match self.receiver.try_recv() {
+            Ok(outcome) => Some(outcome),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(FetchOutcome::Cancelled),
+        }
+    }
+}
+
+impl Drop for PendingFetch {
+    fn drop(&mut self) {
+        // Stops the command if it's still running
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn run(command: &str, timeout: Duration, cancel: &AtomicBool) -> FetchOutcome {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        // Own process group, so a timeout also stops the pipeline's children
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return FetchOutcome::SpawnError(e.to_string()),
+    };
+
+    // Drain pipes on their own threads so a chatty command can't block on a
+    // full pipe. They're only waited for until the timeout, as a background
+    // job the command started can keep them open after it exits.
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let (mut status, mut out, mut err) = (None, None, None);
+    let started = Instant::now();
+
+    loop {
+        if status.is_none() {
+            match child.try_wait() {
+                Ok(exited) => status = exited,
+                Err(e) => return FetchOutcome::SpawnError(e.to_string()),
+            }
+        }
+        out = out.or_else(|| pipe_text(&stdout));
+        err = err.or_else(|| pipe_text(&stderr));
+        if let (Some(status), Some(stdout), Some(stderr)) = (status, &out, &err) {
+            return if status.success() {
+                FetchOutcome::Values(parse_values(stdout))
+            } else {
+                FetchOutcome::Failed {
+                    code: status.code(),
+                    stderr: stderr.trim_end().to_string(),
+                }
+            };
+        }
+
+        if cancel.load(Ordering::Relaxed) {
+            kill(&mut child);
+            return FetchOutcome::Cancelled;
+        }
+        if started.elapsed() >= timeout {
+            kill(&mut child);
+            return FetchOutcome::TimedOut(timeout);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Read all of `pipe` on a new thread, sending the text once it closes
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        let _ = sender.send(String::from_utf8_lossy(&bytes).into_owned());
+    });
+    receiver
+}
+
+/// The text a pipe's reader sent, if it's done
+fn pipe_text(reader: &Receiver<String>) -> Option<String> {
+    match reader.try_recv() {
+        Ok(text) => Some(text),
+        Err(TryRecvError::Empty) => None,
+        Err(TryRecvError::Disconnected) => Some(String::new()),
+    }
+}
+
+/// Stop the command and everything it started, which share its process group
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+        let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Parse fetch output: one option per line, `name\tid` or just `name`
+pub fn parse_values(stdout: &str) -> Vec<(String, String)> {
+    stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (name, id) = line.split_once('\t').unwrap_or((line, ""));
+            (name.trim().to_string(), id.trim().to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait(fetch: &PendingFetch) -> FetchOutcome {
+        loop {
+            if let Some(outcome) = fetch.try_finish() {
+                return outcome;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(
+            parse_values("web\tabc123\n\ndb\n"),
+            vec![
+                ("web".to_string(), "abc123".to_string()),
+                ("db".to_string(), "".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fetch_success() {
+        let fetch = PendingFetch::spawn("printf 'a\\tb\\n'".to_string(), DEFAULT_TIMEOUT);
+        assert_eq!(
+            wait(&fetch),
+            FetchOutcome::Values(vec![("a".to_string(), "b".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_fetch_failure_reports_stderr_and_code() {
+        let fetch = PendingFetch::spawn("echo nope >&2; exit 3".to_string(), DEFAULT_TIMEOUT);
+        let outcome = wait(&fetch);
+        assert_eq!(
+            outcome,
+            FetchOutcome::Failed {
+                code: Some(3),
+                stderr: "nope".to_string()
+            }
+        );
+        assert_eq!(
+            outcome.error_lines(),
+            vec!["Fetch failed (exit code 3)", "nope"]
+        );
+    }
+
+    #[test]
+    fn test_fetch_times_out() {
+        let timeout = Duration::from_millis(100);
+        let fetch = PendingFetch::spawn("sleep 5 | cat".to_string(), timeout);
+        let started = Instant::now();
+        assert_eq!(wait(&fetch), FetchOutcome::TimedOut(timeout));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_fetch_background_job_holding_output_times_out() {
+        let timeout = Duration::from_millis(200);
+        let fetch = PendingFetch::spawn("echo a; sleep 5 &".to_string(), timeout);
+        let started = Instant::now();
+        assert_eq!(wait(&fetch), FetchOutcome::TimedOut(timeout));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_fetch_cancel() {
+        let fetch = PendingFetch::spawn("sleep 5".to_string(), DEFAULT_TIMEOUT);
+        fetch.cancel.store(true, Ordering::Relaxed);
+        assert_eq!(wait(&fetch), FetchOutcome::Cancelled);
+    }
+}
@@ -0,0 +1,144 @@
+const MATCH_SCORE: i64 = 16;
+const CONSECUTIVE_BONUS: i64 = 8;
+const WORD_START_BONUS: i64 = 12;
+const GAP_PENALTY: i64 = 1;
+const MAX_LEADING_PENALTY: i64 = 10;
+
+/// Case-insensitive subsequence match of `query` in `text`, ignoring spaces
+/// in the query. Returns a score (higher is better) and the char positions
+/// in `text` that matched.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let text: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let first = *query.first()?;
+
+    // Try every place the first query char occurs and keep the best-scoring
+    // greedy match, so "log" prefers "Follow logs" over "fo(l)low l(o)(g)s"
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in (0..lower.len()).filter(|&i| lower[i] == first) {
+        let Some(positions) = match_from(&query, &lower, start) else {
+            break; // Later starts can't match either
+        };
+        let score = score(&text, &positions);
+        if best.as_ref().is_none_or(|(s, _)| score > *s) {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+fn match_from(query: &[char], lower: &[char], start: usize) -> Option<Vec<usize>> {
+    let mut positions = Vec::with_capacity(query.len());
+    let mut idx = start;
+    for &q in query {
+        while idx < lower.len() && lower[idx] != q {
+            idx += 1;
+        }
+        if idx == lower.len() {
+            return None;
+        }
+        positions.push(idx);
+        idx += 1;
+    }
+    Some(positions)
+}
+
+fn score(text: &[char], positions: &[usize]) -> i64 {
+    let mut score = -(positions[0] as i64).min(MAX_LEADING_PENALTY);
+    for (n, &pos) in positions.iter().enumerate() {
+        score += MATCH_SCORE;
+        if is_word_start(text, pos) {
+            score += WORD_START_BONUS;
+        }
+        if n > 0 {
+            let prev = positions[n - 1];
+            if pos == prev + 1 {
+                score += CONSECUTIVE_BONUS;
+            } else {
+                score -= (pos - prev - 1) as i64 * GAP_PENALTY;
+            }
+        }
+    }
+    score
+}
+
+fn is_word_start(text: &[char], pos: usize) -> bool {
+    if pos == 0 {
+        return true;
+    }
+    let (prev, cur) = (text[pos - 1], text[pos]);
+    !prev.is_alphanumeric() || (prev.is_lowercase() && cur.is_uppercase())
+}
+
+/// Indices of `items` that match `query`, best match first (ties keep their
+/// original order), with the matched char positions of each
+pub fn rank<S: AsRef<str>>(query: &str, items: &[S]) -> Vec<(usize, Vec<usize>)> {
+    let mut matches: Vec<(i64, usize, Vec<usize>)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| {
+            fuzzy_match(query, item.as_ref()).map(|(score, positions)| (score, i, positions))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches.into_iter().map(|(_, i, p)| (i, p)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_subsequence() {
+        let (_, positions) = fuzzy_match("dtc", "Detached").unwrap();
+        assert_eq!(positions, vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_match_is_case_insensitive() {
+        assert!(fuzzy_match("DETACH", "detached (background)").is_some());
+    }
+
+    #[test]
+    fn test_no_match() {
+        assert!(fuzzy_match("xyz", "Detached").is_none());
+        assert!(fuzzy_match("", "Detached").is_none());
+    }
+
+    #[test]
+    fn test_query_spaces_are_ignored() {
+        assert!(fuzzy_match("follow logs", "Follow pod logs").is_some());
+    }
+
+    #[test]
+    fn test_prefers_word_starts_and_runs() {
+        let (_, positions) = fuzzy_match("log", "Follow logs").unwrap();
+        assert_eq!(positions, vec![7, 8, 9]);
+    }
+
+    #[test]
+    fn test_rank_orders_best_first() {
+        let items = ["Foreground (no TTY)", "Detached (background)", "Debug"];
+        let ranked: Vec<usize> = rank("de", &items).into_iter().map(|(i, _)| i).collect();
+        assert_eq!(ranked, vec![1, 2]);
+
+        let ranked: Vec<usize> = rank("back", &items).into_iter().map(|(i, _)| i).collect();
+        assert_eq!(ranked, vec![1]);
+    }
+
+    #[test]
+    fn test_rank_ties_keep_order() {
+        let items = ["pod-a", "pod-b", "pod-c"];
+        let ranked: Vec<usize> = rank("pod", &items).into_iter().map(|(i, _)| i).collect();
+        assert_eq!(ranked, vec![0, 1, 2]);
+    }
+}
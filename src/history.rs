@@ -0,0 +1,244 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::CommandLine;
+use crate::wizard::AnswerValue;
+
+/// Most unpinned commands shown in a config's Recent menu section
+pub const MAX_RECENT: usize = 5;
+
+/// Most unpinned commands kept per config
+const MAX_KEPT: usize = 50;
+
+/// A command built from a config, as remembered for the Recent menu
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Entry {
+    pub command: String,
+    /// The words it ran as; none for a command run through the shell
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argv: Option<Vec<String>>,
+    /// Step answers in `--answer` form, a list for a multi step, to re-open
+    /// the wizard with. Empty for commands built from presets.
+    #[serde(default)]
+    pub answers: Vec<(String, AnswerValue)>,
+    #[serde(default)]
+    pub pinned: bool,
+    pub count: u32,
+    pub last_used: u64, // Seconds since the Unix epoch
+}
+
+/// Commands built with each config, stored as one JSON file per config
+/// under the user's data dir (e.g. `~/.local/share/i/history/`)
+#[derive(Debug, Clone)]
+pub struct History {
+    dir: Option<PathBuf>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(dirs::data_dir().map(|dir| dir.join("i").join("history")))
+    }
+}
+
+impl History {
+    /// A store under `dir`; `None` keeps nothing, as in tests
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir }
+    }
+
+    /// A config's entries, pinned ones first, then most recently used
+    pub fn load(&self, config: &str) -> Vec<Entry> {
+        let mut entries: Vec<Entry> = self
+            .path(config)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        sort(&mut entries);
+        entries
+    }
+
+    pub fn save(&self, config: &str, entries: &[Entry]) -> std::io::Result<()> {
+        let Some(path) = self.path(config) else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(entries)?)
+    }
+
+    fn path(&self, config: &str) -> Option<PathBuf> {
+        Some(self.dir.as_ref()?.join(format!("{}.json", config)))
+    }
+}
+
+/// Note a use of `command`: a repeat bumps the existing entry, with its
+/// answers replaced by the latest ones. The oldest unpinned entries are
+/// dropped past the limit.
+pub fn record(
+    entries: &mut Vec<Entry>,
+    command: &CommandLine,
+    answers: Vec<(String, AnswerValue)>,
+) {
+    let previous = entries
+        .iter()
+        .position(|e| e.command == command.display)
+        .map(|i| entries.remove(i));
+    // In front, so it also sorts first among commands run the same second
+    entries.insert(
+        0,
+        Entry {
+            command: command.display.clone(),
+            argv: command.argv.clone(),
+            answers,
+            pinned: previous.as_ref().is_some_and(|e| e.pinned),
+            count: previous.map_or(0, |e| e.count) + 1,
+            last_used: now(),
+        },
+    );
+    sort(entries);
+
+    let mut unpinned = 0;
+    entries.retain(|e| {
+        unpinned += usize::from(!e.pinned);
+        e.pinned || unpinned <= MAX_KEPT
+    });
+}
+
+/// Pinned entries first, then the most recently used
+pub fn sort(entries: &mut [Entry]) {
+    entries.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then(b.last_used.cmp(&a.last_used))
+            .then(b.count.cmp(&a.count))
+    });
+}
+
+/// How many of the (sorted) entries the menu shows: every pinned one, and
+/// the most recent others
+pub fn shown(entries: &[Entry]) -> usize {
+    let pinned = entries.iter().take_while(|e| e.pinned).count();
+    pinned + (entries.len() - pinned).min(MAX_RECENT)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(command: &str, last_used: u64, pinned: bool) -> Entry {
+        Entry {
+            command: command.to_string(),
+            argv: None,
+            answers: Vec::new(),
+            pinned,
+            count: 1,
+            last_used,
+        }
+    }
+
+    fn line(command: &str) -> CommandLine {
+        CommandLine::new(&[crate::output::Part::Shell(command.to_string())], false)
+    }
+
+    fn commands(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|e| e.command.as_str()).collect()
+    }
+
+    #[test]
+    fn test_record_new_and_repeated_commands() {
+        let mut entries = vec![entry("ls -a", 10, false)];
+        record(
+            &mut entries,
+            &line("ls -l"),
+            vec![("format".to_string(), AnswerValue::from("Long"))],
+        );
+        assert_eq!(commands(&entries), ["ls -l", "ls -a"]);
+
+        record(&mut entries, &line("ls -a"), Vec::new());
+        assert_eq!(commands(&entries), ["ls -a", "ls -l"]);
+        assert_eq!(entries[0].count, 2);
+        assert_eq!(
+            entries[0].argv,
+            Some(vec!["ls".to_string(), "-a".to_string()])
+        );
+        assert_eq!(entries[1].answers.len(), 1);
+    }
+
+    #[test]
+    fn test_sort_puts_pinned_first() {
+        let mut entries = vec![
+            entry("new", 30, false),
+            entry("old pinned", 10, true),
+            entry("mid", 20, false),
+        ];
+        sort(&mut entries);
+        assert_eq!(commands(&entries), ["old pinned", "new", "mid"]);
+    }
+
+    #[test]
+    fn test_shown_keeps_all_pinned() {
+        let mut entries: Vec<Entry> = (0..8).map(|i| entry(&i.to_string(), i, false)).collect();
+        entries[7].pinned = true;
+        entries[6].pinned = true;
+        sort(&mut entries);
+        assert_eq!(shown(&entries), 2 + MAX_RECENT);
+        assert_eq!(shown(&entries[..3]), 3);
+    }
+
+    #[test]
+    fn test_record_drops_oldest_unpinned() {
+        let mut entries = vec![entry("pinned", 0, true)];
+        for i in 0..MAX_KEPT + 3 {
+            entries.push(entry(&format!("cmd {}", i), i as u64 + 1, false));
+        }
+        record(&mut entries, &line("latest"), Vec::new());
+        assert_eq!(entries.len(), 1 + MAX_KEPT);
+        assert_eq!(entries[0].command, "pinned");
+        assert_eq!(entries[1].command, "latest");
+        assert!(!commands(&entries).contains(&"cmd 0"));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("i-history-store-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let history = History::new(Some(dir.clone()));
+        assert!(history.load("docker-run").is_empty());
+
+        let entries = vec![entry("docker run nginx", 5, false), entry("x", 1, true)];
+        history.save("docker-run", &entries).unwrap();
+        assert_eq!(
+            commands(&history.load("docker-run")),
+            ["x", "docker run nginx"]
+        );
+        assert!(history.load("docker").is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_answers_load_as_strings_or_lists() {
+        let json = r#"[{ "command": "ls -l", "answers": [["format", "Long"], ["cols", ["a, b", "c"]]], "count": 1, "last_used": 0 }]"#;
+        let entries: Vec<Entry> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            entries[0].answers,
+            vec![
+                ("format".to_string(), AnswerValue::from("Long")),
+                (
+                    "cols".to_string(),
+                    AnswerValue::List(vec!["a, b".to_string(), "c".to_string()])
+                ),
+            ]
+        );
+    }
+}
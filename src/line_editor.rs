@@ -0,0 +1,308 @@
+use std::cell::Cell;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Single-line text input with a cursor, readline-style editing keys and
+/// horizontal scrolling
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    text: String,
+    cursor: usize,       // In chars
+    scroll: Cell<usize>, // First char shown, kept across frames like the list viewport
+}
+
+impl LineEditor {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.set("");
+    }
+
+    /// Replace the text, with the cursor at the end
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.len();
+        self.scroll.set(0);
+    }
+
+    /// Insert pasted text at the cursor. Line breaks become spaces, since
+    /// the value ends up on a single command line.
+    pub fn insert_str(&mut self, text: &str) {
+        let text = text.trim_end_matches(['\r', '\n']).replace("\r\n", " ");
+        for c in text.chars() {
+            self.insert(if c == '\n' || c == '\r' { ' ' } else { c });
+        }
+    }
+
+    /// Apply an editing key. Returns false for keys the editor doesn't use,
+    /// such as Enter and Esc.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+
+        match key.code {
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.len(),
+            KeyCode::Char('b') if ctrl => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Char('f') if ctrl => self.cursor = (self.cursor + 1).min(self.len()),
+            KeyCode::Char('b') if alt => self.cursor = self.word_start(),
+            KeyCode::Char('f') if alt => self.cursor = self.word_end(),
+            KeyCode::Char('h') if ctrl => self.delete_back(),
+            KeyCode::Char('d') if ctrl => self.delete_forward(),
+            KeyCode::Char('d') if alt => self.delete_range(self.cursor, self.word_end()),
+            KeyCode::Char('w') if ctrl => self.delete_range(self.space_word_start(), self.cursor),
+            KeyCode::Char('u') if ctrl => self.delete_range(0, self.cursor),
+            KeyCode::Char('k') if ctrl => self.delete_range(self.cursor, self.len()),
+            KeyCode::Char(_) if ctrl || alt => return false,
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Left if ctrl || alt => self.cursor = self.word_start(),
+            KeyCode::Right if ctrl || alt => self.cursor = self.word_end(),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.len(),
+            KeyCode::Backspace if ctrl || alt => {
+                self.delete_range(self.word_start(), self.cursor);
+            }
+            KeyCode::Backspace => self.delete_back(),
+            KeyCode::Delete => self.delete_forward(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// The part of the text that fits in `width` columns (one of them kept
+    /// for the cursor at the end), and the cursor's column within it
+    pub fn view(&self, width: usize) -> (String, usize) {
+        let width = width.max(1);
+        let len = self.len();
+        let mut scroll = self.scroll.get().min(self.cursor);
+        if self.cursor >= scroll + width {
+            scroll = self.cursor + 1 - width;
+        }
+        // Don't leave blank columns after a deletion shortened the text
+        scroll = scroll.min((len + 1).saturating_sub(width));
+        self.scroll.set(scroll);
+
+        let visible = self.text.chars().skip(scroll).take(width).collect();
+        (visible, self.cursor - scroll)
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.text.len())
+    }
+
+    fn insert(&mut self, c: char) {
+        let idx = self.byte_index(self.cursor);
+        self.text.insert(idx, c);
+        self.cursor += 1;
+    }
+
+    fn delete_back(&mut self) {
+        if self.cursor > 0 {
+            self.delete_range(self.cursor - 1, self.cursor);
+        }
+    }
+
+    fn delete_forward(&mut self) {
+        self.delete_range(self.cursor, (self.cursor + 1).min(self.len()));
+    }
+
+    /// Remove chars `start..end` and leave the cursor at `start`
+    fn delete_range(&mut self, start: usize, end: usize) {
+        let (from, to) = (self.byte_index(start), self.byte_index(end));
+        self.text.replace_range(from..to, "");
+        self.cursor = start;
+    }
+
+    /// Start of the word before the cursor, words being runs of alphanumerics
+    fn word_start(&self) -> usize {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut idx = self.cursor;
+        while idx > 0 && !chars[idx - 1].is_alphanumeric() {
+            idx -= 1;
+        }
+        while idx > 0 && chars[idx - 1].is_alphanumeric() {
+            idx -= 1;
+        }
+        idx
+    }
+
+    /// End of the word after the cursor
+    fn word_end(&self) -> usize {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut idx = self.cursor;
+        while idx < chars.len() && !chars[idx].is_alphanumeric() {
+            idx += 1;
+        }
+        while idx < chars.len() && chars[idx].is_alphanumeric() {
+            idx += 1;
+        }
+        idx
+    }
+
+    /// Start of the whitespace-delimited word before the cursor, as Ctrl-W
+    /// deletes it in a shell
+    fn space_word_start(&self) -> usize {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut idx = self.cursor;
+        while idx > 0 && chars[idx - 1].is_whitespace() {
+            idx -= 1;
+        }
+        while idx > 0 && !chars[idx - 1].is_whitespace() {
+            idx -= 1;
+        }
+        idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::default();
+        editor.set(text);
+        editor
+    }
+
+    fn press(editor: &mut LineEditor, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        editor.handle_key(&KeyEvent::new(code, modifiers))
+    }
+
+    fn ctrl(editor: &mut LineEditor, c: char) {
+        assert!(press(editor, KeyCode::Char(c), KeyModifiers::CONTROL));
+    }
+
+    fn key(editor: &mut LineEditor, code: KeyCode) {
+        assert!(press(editor, code, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn test_insert_at_cursor() {
+        let mut e = editor("helo");
+        key(&mut e, KeyCode::Left);
+        key(&mut e, KeyCode::Char('l'));
+        assert_eq!(e.as_str(), "hello");
+        key(&mut e, KeyCode::Home);
+        key(&mut e, KeyCode::Char('>'));
+        assert_eq!(e.as_str(), ">hello");
+    }
+
+    #[test]
+    fn test_shortcut_letters_are_typed() {
+        let mut e = LineEditor::default();
+        for c in "jk q".chars() {
+            key(&mut e, KeyCode::Char(c));
+        }
+        assert_eq!(e.as_str(), "jk q");
+    }
+
+    #[test]
+    fn test_backspace_and_delete_forward() {
+        let mut e = editor("abcd");
+        key(&mut e, KeyCode::Left);
+        key(&mut e, KeyCode::Left);
+        key(&mut e, KeyCode::Backspace);
+        assert_eq!(e.as_str(), "acd");
+        key(&mut e, KeyCode::Delete);
+        assert_eq!(e.as_str(), "ad");
+        ctrl(&mut e, 'd');
+        assert_eq!(e.as_str(), "a");
+    }
+
+    #[test]
+    fn test_ctrl_a_e_u_k() {
+        let mut e = editor("git commit");
+        ctrl(&mut e, 'a');
+        key(&mut e, KeyCode::Right);
+        key(&mut e, KeyCode::Right);
+        key(&mut e, KeyCode::Right);
+        ctrl(&mut e, 'k');
+        assert_eq!(e.as_str(), "git");
+        ctrl(&mut e, 'e');
+        key(&mut e, KeyCode::Char('!'));
+        assert_eq!(e.as_str(), "git!");
+        key(&mut e, KeyCode::Left);
+        ctrl(&mut e, 'u');
+        assert_eq!(e.as_str(), "!");
+    }
+
+    #[test]
+    fn test_ctrl_w_deletes_previous_word() {
+        let mut e = editor("fix the bug  ");
+        ctrl(&mut e, 'w');
+        assert_eq!(e.as_str(), "fix the ");
+        ctrl(&mut e, 'w');
+        assert_eq!(e.as_str(), "fix ");
+    }
+
+    #[test]
+    fn test_word_movement() {
+        let mut e = editor("--name my-app");
+        assert!(press(&mut e, KeyCode::Left, KeyModifiers::CONTROL));
+        assert_eq!(e.cursor, 10);
+        assert!(press(&mut e, KeyCode::Char('b'), KeyModifiers::ALT));
+        assert_eq!(e.cursor, 7);
+        assert!(press(&mut e, KeyCode::Char('f'), KeyModifiers::ALT));
+        assert_eq!(e.cursor, 9);
+        assert!(press(&mut e, KeyCode::Char('d'), KeyModifiers::ALT));
+        assert_eq!(e.as_str(), "--name my");
+    }
+
+    #[test]
+    fn test_multibyte_text() {
+        let mut e = editor("héllo");
+        key(&mut e, KeyCode::Home);
+        key(&mut e, KeyCode::Right);
+        key(&mut e, KeyCode::Delete);
+        assert_eq!(e.as_str(), "hllo");
+    }
+
+    #[test]
+    fn test_paste_flattens_newlines() {
+        let mut e = editor("msg: ");
+        e.insert_str("first\nsecond\r\n");
+        assert_eq!(e.as_str(), "msg: first second");
+    }
+
+    #[test]
+    fn test_unhandled_keys() {
+        let mut e = editor("x");
+        assert!(!press(&mut e, KeyCode::Enter, KeyModifiers::NONE));
+        assert!(!press(&mut e, KeyCode::Esc, KeyModifiers::NONE));
+        assert!(!press(&mut e, KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert_eq!(e.as_str(), "x");
+    }
+
+    #[test]
+    fn test_view_scrolls_horizontally() {
+        let mut e = editor("0123456789");
+        // Cursor at the end: last 4 chars + cursor cell
+        assert_eq!(e.view(5), ("6789".to_string(), 4));
+
+        // Moving left inside the view doesn't scroll
+        key(&mut e, KeyCode::Left);
+        key(&mut e, KeyCode::Left);
+        assert_eq!(e.view(5), ("6789".to_string(), 2));
+
+        key(&mut e, KeyCode::Home);
+        assert_eq!(e.view(5), ("01234".to_string(), 0));
+
+        assert_eq!(editor("abc").view(5), ("abc".to_string(), 3));
+    }
+}
@@ -1,53 +1,478 @@
+mod cache;
+mod check;
 mod config;
+mod fetch;
+mod fuzzy;
+mod history;
+mod line_editor;
 mod output;
+mod search;
+mod settings;
+mod shell;
+mod shell_history;
+mod widget;
 mod wizard;
 
+use std::path::{Path, PathBuf};
+
 use clap::Parser;
-use wizard::WizardResult;
+use output::{CommandLine, OutputMode};
+use settings::Settings;
+use wizard::{Wizard, WizardResult};
 
 #[derive(Parser, Debug)]
 #[command(name = "i")]
 #[command(about = "Interactive TUI for CLI commands")]
 struct Args {
-    /// The command to make interactive (e.g., "ls" or "git commit")
-    #[arg(required = true)]
+    /// The command to make interactive (e.g., "ls" or "git commit"); without
+    /// one, pick from every config
     command: Vec<String>,
+
+    /// Answer a step without opening the TUI (e.g., --answer mode=Detached)
+    #[arg(long = "answer", value_name = "STEP=VALUE")]
+    answer: Vec<String>,
+
+    /// JSON file mapping step ids to answers, applied without opening the TUI
+    #[arg(long, value_name = "FILE")]
+    answers: Option<PathBuf>,
+
+    /// What to do with the command built from --answer/--answers
+    #[arg(long, value_enum, default_value = "print")]
+    output: OutputMode,
+
+    /// Write the chosen command to FILE instead of running or printing it
+    #[arg(long, value_name = "FILE")]
+    output_file: Option<PathBuf>,
+
+    /// Open the config matching a command line being edited (e.g., "docker run -d")
+    #[arg(long, value_name = "LINE", conflicts_with = "command")]
+    buffer: Option<String>,
+
+    /// List every config, and whether it's bundled, the project's or the user's
+    #[arg(long, exclusive = true)]
+    list: bool,
+
+    /// Search every config's presets, steps and options, and open the match picked
+    #[arg(long, value_name = "QUERY", conflicts_with_all = ["command", "buffer"])]
+    search: Option<String>,
+
+    /// Check config files (by default, every one outside the binary) for mistakes
+    #[arg(long, value_name = "FILE", num_args = 0.., exclusive = true)]
+    check: Option<Vec<PathBuf>>,
+
+    /// Print the merged config for the command, and which file each part came from
+    #[arg(long)]
+    show_config: bool,
+
+    /// Print the shell code for a Ctrl-X i key binding that opens the wizard
+    #[arg(long, value_enum, value_name = "SHELL", exclusive = true)]
+    init: Option<shell_history::Shell>,
 }
 
+// Exit codes for `i`'s own outcomes. When a command is run, `i` exits with
+// the command's code instead (128 + N if signal N killed it).
+const EXIT_USAGE: i32 = 64; // Invalid arguments or answers
+const EXIT_CONFIG_INVALID: i32 = 65; // Config couldn't be read or parsed
+const EXIT_CONFIG_NOT_FOUND: i32 = 66;
+const EXIT_ERROR: i32 = 125; // Anything else, e.g. no terminal or clipboard
+const EXIT_QUIT: i32 = 1; // Quit without choosing a command
+
+/// A problem with the command-line arguments that clap can't catch
+#[derive(Debug)]
+struct UsageError(String);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    let code = match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            error_exit_code(e.as_ref())
+        }
+    };
+    std::process::exit(code);
+}
+
+fn error_exit_code(error: &(dyn std::error::Error + 'static)) -> i32 {
+    if let Some(error) = error.downcast_ref::<config::ConfigError>() {
+        return match error {
+            config::ConfigError::NotFound(..) => EXIT_CONFIG_NOT_FOUND,
+            _ => EXIT_CONFIG_INVALID,
+        };
+    }
+    if let Some(error) = error.downcast_ref::<output::SpawnError>() {
+        return error.exit_code();
+    }
+    // As `sh` reports a command it found but can't run
+    if error.is::<output::NeedsShell>() {
+        return 126;
     }
+    if error.is::<UsageError>() || error.is::<wizard::AnswerError>() {
+        return EXIT_USAGE;
+    }
+    EXIT_ERROR
 }
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
-    let mut command = args.command;
-    let mut history: Vec<Vec<String>> = Vec::new();
+fn run() -> Result<i32, Box<dyn std::error::Error>> {
+    let mut args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            e.print()?;
+            return Ok(if e.use_stderr() { EXIT_USAGE } else { 0 });
+        }
+    };
+
+    if let Some(shell) = args.init {
+        print!("{}", widget::script(shell));
+        return Ok(0);
+    }
 
-    loop {
-        let config = config::Config::load(&command)?;
+    if let Some(files) = &args.check {
+        return Ok(check_configs(files));
+    }
 
-        match wizard::run(config, command.clone())? {
-            WizardResult::Command(cmd, mode) => {
-                output::handle_output(&cmd, mode)?;
-                break;
-            }
-            WizardResult::Chain(next_config) => {
-                history.push(command.clone());
-                command = next_config.split('-').map(String::from).collect();
+    if args.list {
+        print!("{}", list_configs(&config::list_configs()));
+        return Ok(0);
+    }
+
+    // Settings are only preferences, so a broken file shouldn't stop the wizard
+    let settings = Settings::load().unwrap_or_else(|e| {
+        eprintln!("Warning: {}; using the default settings", e);
+        Settings::default()
+    });
+    if let Some(buffer) = &args.buffer {
+        args.command = buffer_command(buffer);
+    }
+
+    let headless = !args.answer.is_empty() || args.answers.is_some();
+    if args.command.is_empty() && (args.show_config || headless) {
+        return Err(
+            UsageError("--show-config, --answer and --answers need a command".to_string()).into(),
+        );
+    }
+
+    if args.show_config {
+        let (config, origins) = config::Config::load_with_origins(&args.command.join("-"))?;
+        print!("{}", config::explain(&config, &origins));
+        return Ok(0);
+    }
+
+    if headless {
+        return run_headless(&args, &settings);
+    }
+
+    let mut cache = cache::FetchCache::default();
+    let command_history = history::History::default();
+    // Wizards chained from, kept as they were for Back
+    let mut stack = vec![if let Some(query) = &args.search {
+        search_wizard(query, &command_history)
+    } else if args.command.is_empty() {
+        let config = config::picker_config(&config::list_configs());
+        let base_command = config.command_words();
+        Wizard::open(config, base_command, &command_history)
+    } else {
+        let config = config::Config::load(&args.command.join("-"))?;
+        let base_command = config.command_words();
+        Wizard::open(config, base_command, &command_history)
+    }];
+
+    while let Some(wizard) = stack.last_mut() {
+        match wizard::run(wizard, &mut cache)? {
+            WizardResult::Command(cmd, mode) => return finish(&cmd, mode, &args, &settings),
+            WizardResult::Chain(chain) => {
+                let config = config::Config::load(&chain.config)?;
+                let next = wizard.open_chain(&chain, config, &command_history);
+                stack.push(next);
             }
             WizardResult::Back => {
-                if let Some(prev_command) = history.pop() {
-                    command = prev_command;
-                } else {
-                    break;
-                }
+                stack.pop();
+            }
+            WizardResult::Search => stack.push(search_wizard("", &command_history)),
+            WizardResult::Quit => return Ok(EXIT_QUIT),
+        }
+    }
+    Ok(EXIT_QUIT)
+}
+
+/// The wizard for searching every config, filtered by `query`
+fn search_wizard(query: &str, history: &history::History) -> Wizard {
+    let config = search::search_config(&config::load_all());
+    let base_command = config.command_words();
+    let mut wizard = Wizard::open(config, base_command, history);
+    wizard.start_filter(query);
+    wizard
+}
+
+fn run_headless(args: &Args, settings: &Settings) -> Result<i32, Box<dyn std::error::Error>> {
+    let mut values = Vec::new();
+    if let Some(path) = &args.answers {
+        values.extend(load_answers_file(path)?);
+    }
+    for answer in &args.answer {
+        let (id, value) = answer.split_once('=').ok_or_else(|| {
+            UsageError(format!(
+                "Invalid --answer '{}': expected STEP=VALUE",
+                answer
+            ))
+        })?;
+        values.push((id.to_string(), wizard::AnswerValue::from(value)));
+    }
+
+    let config = config::Config::load(&args.command.join("-"))?;
+    let base_command = config.command_words();
+    let cmd = wizard::run_headless(config, base_command, &values)?;
+    finish(&cmd, args.output, args, settings)
+}
+
+/// Print what's wrong with config files, or the default ones, and exit with
+/// EXIT_CONFIG_INVALID if anything is
+fn check_configs(files: &[PathBuf]) -> i32 {
+    let files = if files.is_empty() {
+        config::config_files()
+    } else {
+        files.to_vec()
+    };
+    let diagnostics = check::check_files(&files);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    let plural = |n: usize, noun: &str| format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" });
+    if diagnostics.is_empty() {
+        println!(
+            "Checked {}, no problems found",
+            plural(files.len(), "config file")
+        );
+        0
+    } else {
+        println!(
+            "Found {} in {}",
+            plural(diagnostics.len(), "problem"),
+            plural(files.len(), "config file")
+        );
+        EXIT_CONFIG_INVALID
+    }
+}
+
+/// `--list` output: each config's name, description, where its files were
+/// found (`bundled, user`) and the file that takes priority (`(bundled)/...`
+/// for configs compiled in)
+fn list_configs(configs: &[config::ConfigSummary]) -> String {
+    let sources: Vec<String> = configs
+        .iter()
+        .map(|c| {
+            c.files
+                .iter()
+                .map(|(source, _)| source.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+    let name_width = configs.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    let description_width = configs
+        .iter()
+        .map(|c| c.description.chars().count())
+        .max()
+        .unwrap_or(0);
+    let sources_width = sources.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut list = String::new();
+    for (config, sources) in configs.iter().zip(&sources) {
+        let path = config
+            .files
+            .last()
+            .map(|(_, path)| path.display().to_string())
+            .unwrap_or_default();
+        let line = format!(
+            "{:<name_width$}  {:<description_width$}  {:<sources_width$}  {}",
+            config.name, config.description, sources, path
+        );
+        list.push_str(line.trim_end());
+        list.push('\n');
+    }
+    list
+}
+
+/// The config to open for a command line a shell widget passed in: the
+/// longest run of its leading words that has one. An empty prompt gives no
+/// command, so the picker opens as it does for a bare `i`.
+fn buffer_command(buffer: &str) -> Vec<String> {
+    let words = shell::split(buffer)
+        .unwrap_or_else(|| buffer.split_whitespace().map(String::from).collect());
+    match config::longest_config_prefix(&words) {
+        Some(prefix) => prefix.to_vec(),
+        None => words.into_iter().take(1).collect(),
+    }
+}
+
+/// Hand the command to the output mode, or to --output-file. A command that
+/// runs is added to the shell's history if the user asked for that; one that
+/// couldn't be started isn't.
+fn finish(
+    cmd: &CommandLine,
+    mode: OutputMode,
+    args: &Args,
+    settings: &Settings,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    if let Some(path) = &args.output_file {
+        if mode != OutputMode::Clipboard {
+            output::write_to(cmd, path)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            return Ok(0);
+        }
+    }
+    let add_to_shell_history = || {
+        if let Err(e) = shell_history::append(&settings.shell_history, &cmd.display) {
+            eprintln!("Warning: couldn't add the command to shell history: {}", e);
+        }
+    };
+    match mode {
+        // exec only returns if the command couldn't start, so check first
+        OutputMode::Exec if cfg!(unix) => {
+            if cmd.startable() {
+                add_to_shell_history();
             }
-            WizardResult::Quit => break,
+            output::handle_output(cmd, mode)
         }
+        OutputMode::Execute | OutputMode::Exec => {
+            let code = output::handle_output(cmd, mode)?;
+            add_to_shell_history();
+            Ok(code)
+        }
+        OutputMode::Print | OutputMode::Clipboard => output::handle_output(cmd, mode),
     }
+}
+
+/// Read a JSON object of step id -> answer. Arrays are used for multi steps;
+/// booleans and numbers are accepted for toggles and text.
+fn load_answers_file(path: &Path) -> Result<Vec<(String, wizard::AnswerValue)>, UsageError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| UsageError(format!("Failed to read {}: {}", path.display(), e)))?;
+    let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&content)
+        .map_err(|e| UsageError(format!("Failed to parse {}: {}", path.display(), e)))?;
+
+    map.into_iter()
+        .map(|(id, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => wizard::AnswerValue::One(s),
+                serde_json::Value::Bool(b) => wizard::AnswerValue::One(b.to_string()),
+                serde_json::Value::Number(n) => wizard::AnswerValue::One(n.to_string()),
+                serde_json::Value::Array(items) => wizard::AnswerValue::List(
+                    items
+                        .into_iter()
+                        .map(|item| match item {
+                            serde_json::Value::String(s) => s,
+                            other => other.to_string(),
+                        })
+                        .collect(),
+                ),
+                other => {
+                    return Err(UsageError(format!(
+                        "Unsupported value for '{}' in {}: {}",
+                        id,
+                        path.display(),
+                        other
+                    )))
+                }
+            };
+            Ok((id, value))
+        })
+        .collect()
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_exit_codes() {
+        let not_found = config::ConfigError::NotFound("nope".to_string(), vec![]);
+        assert_eq!(error_exit_code(&not_found), EXIT_CONFIG_NOT_FOUND);
+
+        let invalid = config::ConfigError::ParseError(PathBuf::from("x.json"), "eof".to_string());
+        assert_eq!(error_exit_code(&invalid), EXIT_CONFIG_INVALID);
+
+        let answer = wizard::AnswerError::UnknownStep("x".to_string());
+        assert_eq!(error_exit_code(&answer), EXIT_USAGE);
+        assert_eq!(error_exit_code(&UsageError("bad".to_string())), EXIT_USAGE);
+
+        let other: Box<dyn std::error::Error> = "no terminal".into();
+        assert_eq!(error_exit_code(other.as_ref()), EXIT_ERROR);
+    }
+
+    #[test]
+    fn test_buffer_opens_longest_matching_config() {
+        let words = buffer_command;
+        assert_eq!(words("docker run -d nginx"), ["docker", "run"]);
+        assert_eq!(words("git commit -m 'wip'"), ["git", "commit"]);
+        assert_eq!(words("docker"), ["docker"]);
+        assert_eq!(words("nosuchtool --flag"), ["nosuchtool"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_quit_differs_from_an_interrupted_command() {
+        let status = std::process::Command::new("sh")
+            .args(["-c", "kill -INT $$"])
+            .status()
+            .unwrap();
+        assert_eq!(output::exit_code(status), 130);
+        assert_ne!(EXIT_QUIT, output::exit_code(status));
+        assert!(!(126..=165).contains(&EXIT_QUIT));
+    }
+
+    #[test]
+    fn test_empty_buffer_opens_the_picker() {
+        assert!(buffer_command("").is_empty());
+        assert!(buffer_command("  \t").is_empty());
+    }
+
+    #[test]
+    fn test_list_aligns_descriptions_and_paths() {
+        let summary = |name: &str, description: &str, files| config::ConfigSummary {
+            name: name.to_string(),
+            description: description.to_string(),
+            files,
+        };
+        let configs = [
+            summary(
+                "ls",
+                "List files",
+                vec![
+                    (config::Source::Bundled, PathBuf::from("(bundled)/ls.json")),
+                    (
+                        config::Source::User,
+                        PathBuf::from("/home/me/.config/i/ls.json"),
+                    ),
+                ],
+            ),
+            summary(
+                "kubectl",
+                "Kubernetes",
+                vec![(
+                    config::Source::Project,
+                    PathBuf::from("/repo/.i/kubectl.json"),
+                )],
+            ),
+            summary("docker-run", "", vec![]),
+        ];
+        assert_eq!(
+            list_configs(&configs),
+            "ls          List files  bundled, user  /home/me/.config/i/ls.json\n\
+             kubectl     Kubernetes  project        /repo/.i/kubectl.json\n\
+             docker-run\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_answers_file_is_usage_error() {
+        let err = load_answers_file(Path::new("/nonexistent/answers.json")).unwrap_err();
+        assert!(err.to_string().contains("Failed to read"));
+    }
 }
@@ -1,54 +1,340 @@
-use std::process::Command;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::config::Quote;
+use crate::shell;
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
 pub enum OutputMode {
     Print,
     Clipboard,
     Execute,
+    /// Replace `i` with the command (Unix), so it owns the terminal,
+    /// signals and exit code as if it had been typed by hand
+    Exec,
+}
+
+/// A piece of a command as the wizard builds it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    /// Shell text from a config, like a flag or a preset's flags. It runs as
+    /// the words `sh` would split it into, once its placeholders are filled.
+    Shell(String),
+    /// Words that run as they are, shown quoted. `args` is `None` for a
+    /// value only a shell can expand, like a glob.
+    Words {
+        shown: String,
+        args: Option<Vec<String>>,
+    },
 }
 
-pub fn handle_output(command: &str, mode: OutputMode) -> Result<(), Box<dyn std::error::Error>> {
+impl Part {
+    /// One word, quoted for display
+    pub fn word(word: &str) -> Part {
+        Part::Words {
+            shown: shell::quote(word),
+            args: Some(vec![word.to_string()]),
+        }
+    }
+
+    /// A typed value, quoted the way its step asks for. Unquoted, it's
+    /// shell text like any other in the config.
+    pub fn typed(value: &str, mode: Quote) -> Part {
+        let shown = shell::quote_as(value, mode);
+        let args = match mode {
+            Quote::None => return Part::Shell(shown),
+            Quote::Auto => shell::expand_auto(value).map(|word| vec![word]),
+            Quote::Single | Quote::Double => Some(vec![value.to_string()]),
+        };
+        Part::Words { shown, args }
+    }
+
+    pub fn shown(&self) -> &str {
+        match self {
+            Part::Shell(text) => text,
+            Part::Words { shown, .. } => shown,
+        }
+    }
+
+    /// Fill in `placeholder`, escaped for the quotes around it. Only shell
+    /// text has placeholders; typed words are taken literally.
+    pub fn substitute(&self, placeholder: &str, value: &str) -> Part {
+        match self {
+            Part::Shell(text) => Part::Shell(shell::substitute(text, placeholder, value)),
+            words => words.clone(),
+        }
+    }
+
+    /// The words it runs as, if it can run without a shell
+    pub fn args(&self) -> Option<Vec<String>> {
+        match self {
+            Part::Shell(text) => shell::split_args(text),
+            Part::Words { args, .. } => args.clone(),
+        }
+    }
+}
+
+/// How the parts of a command read on the command line
+pub fn display(parts: &[Part]) -> String {
+    parts
+        .iter()
+        .map(Part::shown)
+        .filter(|shown| !shown.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A built command: the text shown to the user, and the words to run it
+/// with. Only a config that opts in with `"shell": true` runs its commands
+/// through `sh -c`; any other command that uses shell syntax, and so has no
+/// argv, can be printed or copied but not run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandLine {
+    pub display: String,
+    pub argv: Option<Vec<String>>,
+    pub shell: bool,
+}
+
+impl CommandLine {
+    pub fn new(parts: &[Part], shell: bool) -> Self {
+        let argv = if shell {
+            None
+        } else {
+            parts
+                .iter()
+                .map(Part::args)
+                .collect::<Option<Vec<_>>>()
+                .map(|args| args.concat())
+                .filter(|argv| !argv.is_empty())
+        };
+        CommandLine {
+            display: display(parts),
+            argv,
+            shell,
+        }
+    }
+
+    /// Whether the command can be run, rather than only printed or copied
+    pub fn runnable(&self) -> bool {
+        self.shell || self.argv.is_some()
+    }
+
+    /// Whether running the command should get as far as starting it: it's
+    /// runnable and its program can be found. For checks that have to come
+    /// before an exec, which doesn't return once it works.
+    pub fn startable(&self) -> bool {
+        match &self.argv {
+            Some(argv) => find_program(&argv[0]),
+            None => self.shell,
+        }
+    }
+
+    fn to_process(&self) -> Result<Command, NeedsShell> {
+        match &self.argv {
+            Some(argv) => {
+                let mut cmd = Command::new(&argv[0]);
+                cmd.args(&argv[1..]);
+                Ok(cmd)
+            }
+            None if !self.shell => Err(NeedsShell(self.display.clone())),
+            None if cfg!(target_os = "windows") => {
+                let mut cmd = Command::new("cmd");
+                cmd.args(["/C", &self.display]);
+                Ok(cmd)
+            }
+            None => {
+                let mut cmd = Command::new("sh");
+                cmd.args(["-c", &self.display]);
+                Ok(cmd)
+            }
+        }
+    }
+
+    fn program(&self) -> &str {
+        match &self.argv {
+            Some(argv) => &argv[0],
+            None => "sh",
+        }
+    }
+}
+
+/// Print, copy or run the command. Returns the exit code `i` should end
+/// with: the command's own when it was run, 0 otherwise.
+pub fn handle_output(
+    command: &CommandLine,
+    mode: OutputMode,
+) -> Result<i32, Box<dyn std::error::Error>> {
     match mode {
         OutputMode::Print => {
-            println!("{}", command);
+            println!("{}", command.display);
         }
         OutputMode::Clipboard => {
             let mut clipboard = arboard::Clipboard::new()?;
-            clipboard.set_text(command)?;
+            clipboard.set_text(&command.display)?;
             eprintln!("Command copied to clipboard");
         }
         OutputMode::Execute => {
-            eprintln!("{}", command);
-            let status = if cfg!(target_os = "windows") {
-                Command::new("cmd").args(["/C", command]).status()?
-            } else {
-                Command::new("sh").args(["-c", command]).status()?
-            };
-
-            if !status.success() {
-                return Err(format!("Command exited with status: {}", status).into());
+            let process = command.to_process()?;
+            eprintln!("{}", command.display);
+            return Ok(run(command, process)?);
+        }
+        OutputMode::Exec => {
+            #[allow(unused_mut)]
+            let mut process = command.to_process()?;
+            eprintln!("{}", command.display);
+            #[cfg(unix)]
+            {
+                use std::os::unix::process::CommandExt;
+                // Only returns if the program couldn't be started
+                let error = process.exec();
+                return Err(SpawnError::new(command, error).into());
             }
+            #[cfg(not(unix))]
+            return Ok(run(command, process)?);
+        }
+    }
+    Ok(0)
+}
+
+/// Write the command to `path` (a temp file, or `/dev/fd/N`) for a shell
+/// widget to put on the prompt line
+pub fn write_to(command: &CommandLine, path: &Path) -> io::Result<()> {
+    fs::write(path, &command.display)
+}
+
+/// Whether `program` names an executable file, directly or on `$PATH`
+fn find_program(program: &str) -> bool {
+    let executable = |path: &Path| {
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+        }
+        #[cfg(not(unix))]
+        metadata.is_file()
+    };
+    if program.contains(std::path::MAIN_SEPARATOR) || program.contains('/') {
+        return executable(Path::new(program));
+    }
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| executable(&dir.join(program)))
+    })
+}
+
+fn run(command: &CommandLine, mut process: Command) -> Result<i32, SpawnError> {
+    let status = process
+        .status()
+        .map_err(|error| SpawnError::new(command, error))?;
+    Ok(exit_code(status))
+}
+
+/// The exit code a shell would report for a finished command: its own
+/// code, or 128 + the signal number if a signal killed it
+pub fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
         }
     }
-    Ok(())
+    status.code().unwrap_or(1)
 }
 
+/// A command that couldn't be started at all
+#[derive(Debug)]
+pub struct SpawnError {
+    program: String,
+    error: io::Error,
+}
+
+impl SpawnError {
+    fn new(command: &CommandLine, error: io::Error) -> Self {
+        SpawnError {
+            program: command.program().to_string(),
+            error,
+        }
+    }
+
+    /// 127 if the program doesn't exist, 126 if it can't be executed, as in `sh`
+    pub fn exit_code(&self) -> i32 {
+        match self.error.kind() {
+            io::ErrorKind::NotFound => 127,
+            _ => 126,
+        }
+    }
+}
+
+impl std::fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to run {}: {}", self.program, self.error)
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
+/// A command with shell syntax, from a config that doesn't run its commands
+/// through a shell
+#[derive(Debug)]
+pub struct NeedsShell(String);
+
+impl std::fmt::Display for NeedsShell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Can't run `{}` without a shell (it has a glob, `~user` or other shell syntax). \
+             Print or copy it instead, or set \"shell\": true in its config.",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for NeedsShell {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn line(command: &str) -> CommandLine {
+        CommandLine::new(&[Part::Shell(command.to_string())], false)
+    }
+
+    fn shell_line(command: &str) -> CommandLine {
+        CommandLine::new(&[Part::Shell(command.to_string())], true)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_startable_needs_a_program_that_exists() {
+        assert!(line("sh -c true").startable());
+        assert!(line("/bin/sh -c true").startable());
+        assert!(!line("no-such-program-i-test --flag").startable());
+        assert!(!line("/no/such/program").startable());
+        // Needs a shell it doesn't have
+        assert!(!line("ls | wc -l").startable());
+        assert!(shell_line("ls | wc -l").startable());
+    }
+
     #[test]
     fn test_output_mode_equality() {
         assert_eq!(OutputMode::Print, OutputMode::Print);
         assert_eq!(OutputMode::Clipboard, OutputMode::Clipboard);
         assert_eq!(OutputMode::Execute, OutputMode::Execute);
+        assert_eq!(OutputMode::Exec, OutputMode::Exec);
 
         assert_ne!(OutputMode::Print, OutputMode::Clipboard);
         assert_ne!(OutputMode::Print, OutputMode::Execute);
         assert_ne!(OutputMode::Clipboard, OutputMode::Execute);
+        assert_ne!(OutputMode::Execute, OutputMode::Exec);
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn test_output_mode_clone() {
         let mode = OutputMode::Execute;
         let cloned = mode.clone();
@@ -67,28 +353,122 @@ mod tests {
         assert_eq!(format!("{:?}", OutputMode::Print), "Print");
         assert_eq!(format!("{:?}", OutputMode::Clipboard), "Clipboard");
         assert_eq!(format!("{:?}", OutputMode::Execute), "Execute");
+        assert_eq!(format!("{:?}", OutputMode::Exec), "Exec");
     }
 
     #[test]
     fn test_handle_output_print() {
         // Print mode should succeed (writes to stdout)
-        let result = handle_output("echo test", OutputMode::Print);
+        let result = handle_output(&line("echo test"), OutputMode::Print);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_handle_output_execute_success() {
         // Execute a simple command that should succeed
-        let result = handle_output("true", OutputMode::Execute);
-        assert!(result.is_ok());
+        let result = handle_output(&line("true"), OutputMode::Execute);
+        assert_eq!(result.unwrap(), 0);
     }
 
     #[test]
     fn test_handle_output_execute_failure() {
-        // Execute a command that returns non-zero exit status
-        let result = handle_output("false", OutputMode::Execute);
-        assert!(result.is_err());
-        let err = result.unwrap_err().to_string();
-        assert!(err.contains("Command exited with status"));
+        // A non-zero exit status is passed on, not treated as an error
+        let result = handle_output(&line("false"), OutputMode::Execute);
+        assert_eq!(result.unwrap(), 1);
+        let result = handle_output(&line("sh -c 'exit 42'"), OutputMode::Execute);
+        assert_eq!(result.unwrap(), 42);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_handle_output_execute_killed_by_signal() {
+        let result = handle_output(&line("sh -c 'kill -TERM $$'"), OutputMode::Execute);
+        assert_eq!(result.unwrap(), 128 + 15);
+    }
+
+    #[test]
+    fn test_write_to_file() {
+        let path = std::env::temp_dir().join(format!("i-output-test-{}", std::process::id()));
+        write_to(&line("git commit -m 'wip'"), &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "git commit -m 'wip'");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_command_line_argv() {
+        let words = |words: &[&str]| Some(words.iter().map(|w| w.to_string()).collect());
+        let parts = [
+            Part::Shell("git commit -m".to_string()),
+            Part::typed("fix it; rm -rf ~", Quote::Auto),
+        ];
+        let cmd = CommandLine::new(&parts, false);
+        assert_eq!(cmd.display, "git commit -m 'fix it; rm -rf ~'");
+        assert_eq!(
+            cmd.argv,
+            words(&["git", "commit", "-m", "fix it; rm -rf ~"])
+        );
+
+        // Typed words are kept apart, whatever they look like
+        let parts = [
+            Part::Shell("echo".to_string()),
+            Part::typed("<x>", Quote::Single),
+        ];
+        assert_eq!(
+            CommandLine::new(&parts, false).argv,
+            words(&["echo", "<x>"])
+        );
+
+        // Shell syntax has no argv; only a config that asks for the shell runs it
+        let glob = [
+            Part::Shell("ls".to_string()),
+            Part::typed("*.log", Quote::Auto),
+        ];
+        assert_eq!(CommandLine::new(&glob, false).display, "ls *.log");
+        assert!(!CommandLine::new(&glob, false).runnable());
+        assert!(CommandLine::new(&glob, true).runnable());
+        assert_eq!(shell_line("ls").argv, None);
+        assert_eq!(line("").argv, None);
+    }
+
+    #[test]
+    fn test_part_substitute_fills_shell_text_only() {
+        let flags = Part::Shell("-l app=<app>".to_string());
+        assert_eq!(
+            flags.substitute("<app>", "my app").shown(),
+            "-l app='my app'"
+        );
+        let typed = Part::typed("<app>", Quote::Auto);
+        assert_eq!(typed.substitute("<app>", "web"), typed);
+    }
+
+    #[test]
+    fn test_handle_output_execute_direct_keeps_words() {
+        // `test` only succeeds if each side arrives as one argument
+        let result = handle_output(&line("test 'a  b' = 'a  b'"), OutputMode::Execute);
+        assert_eq!(result.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_handle_output_execute_through_shell() {
+        let result = handle_output(&shell_line("true | false"), OutputMode::Execute);
+        assert_eq!(result.unwrap(), 1);
+        let result = handle_output(&shell_line("false || true"), OutputMode::Execute);
+        assert_eq!(result.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_handle_output_shell_syntax_needs_shell_config() {
+        let cmd = line("true | false");
+        let err = handle_output(&cmd, OutputMode::Execute).unwrap_err();
+        assert!(err.is::<NeedsShell>());
+        assert!(handle_output(&cmd, OutputMode::Print).is_ok());
+    }
+
+    #[test]
+    fn test_handle_output_execute_missing_program() {
+        let cmd = line("i-no-such-program");
+        let err = run(&cmd, cmd.to_process().unwrap()).unwrap_err();
+        assert_eq!(err.exit_code(), 127);
+        assert!(err.to_string().contains("Failed to run i-no-such-program"));
     }
 }
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use crate::config::{Chain, Config, Quote, Step, StepOption, StepType};
+
+/// A config for searching every config's presets, step prompts and option
+/// labels at once. Each match is an option chaining to its config, with the
+/// preset highlighted or the step answered; the wizard's filter ranks them.
+pub fn search_config(configs: &[Config]) -> Config {
+    Config {
+        name: "i".to_string(),
+        command: "i".to_string(),
+        description: String::new(),
+        steps: vec![Step {
+            id: "match".to_string(),
+            prompt: "Search presets, steps and options:".to_string(),
+            step_type: StepType::Choice,
+            options: configs.iter().flat_map(entries).collect(),
+            flag: None,
+            default: None,
+            when: None,
+            placeholder: None,
+            quote: Quote::Auto,
+        }],
+        presets: Vec::new(),
+        placeholder_options: HashMap::new(),
+        shell: false,
+    }
+}
+
+/// What search can find in one config. The text searched for comes first in
+/// each label, so the ranking favours it over the config's name.
+fn entries(config: &Config) -> Vec<StepOption> {
+    let mut entries = Vec::new();
+    let entry = |label: String, chain: Chain| StepOption {
+        label,
+        flag: None,
+        chain: Some(chain),
+    };
+
+    for preset in &config.presets {
+        let chain = Chain {
+            preset: Some(preset.label.clone()),
+            ..Chain::from(config.name.as_str())
+        };
+        entries.push(entry(
+            format!("{}  — {} preset", preset.label, config.name),
+            chain,
+        ));
+    }
+
+    for step in &config.steps {
+        // Whatever the step needs answered to be shown
+        let mut answers: HashMap<String, String> = step.when.clone().unwrap_or_default();
+        let chain = |answers: HashMap<String, String>| Chain {
+            answers,
+            ..Chain::from(config.name.as_str())
+        };
+
+        match step.step_type {
+            StepType::Choice | StepType::Multi => {
+                for option in &step.options {
+                    let mut answers = answers.clone();
+                    answers.insert(step.id.clone(), option.label.clone());
+                    entries.push(entry(
+                        format!("{}  — {}: {}", option.label, config.name, step.prompt),
+                        chain(answers),
+                    ));
+                }
+            }
+            StepType::Toggle => {
+                answers.insert(step.id.clone(), "yes".to_string());
+                entries.push(entry(
+                    format!("{}  — {}", step.prompt, config.name),
+                    chain(answers),
+                ));
+            }
+            StepType::Text => entries.push(entry(
+                format!("{}  — {}", step.prompt, config.name),
+                chain(answers),
+            )),
+        }
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(json: &str) -> Config {
+        let mut config: Config = serde_json::from_str(json).unwrap();
+        config.name = "docker-logs".to_string();
+        config
+    }
+
+    #[test]
+    fn test_entries_for_presets_steps_and_options() {
+        let config = load(
+            r#"{
+                "command": "docker logs",
+                "steps": [
+                    { "id": "follow", "prompt": "Follow log output?", "type": "toggle", "flag": "-f" },
+                    { "id": "since", "prompt": "Since:", "type": "choice", "options": [
+                        { "label": "All" },
+                        { "label": "Last hour", "flag": "--since 1h" }
+                    ], "when": { "follow": "false" } },
+                    { "id": "container", "prompt": "Container:", "type": "text" }
+                ],
+                "presets": [{ "label": "Tail 100 lines", "flags": "--tail 100" }]
+            }"#,
+        );
+        let entries = entries(&config);
+        let labels: Vec<&str> = entries.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "Tail 100 lines  — docker-logs preset",
+                "Follow log output?  — docker-logs",
+                "All  — docker-logs: Since:",
+                "Last hour  — docker-logs: Since:",
+                "Container:  — docker-logs",
+            ]
+        );
+
+        let chain = |i: usize| entries[i].chain.clone().unwrap();
+        assert_eq!(chain(0).preset.as_deref(), Some("Tail 100 lines"));
+        assert_eq!(chain(1).answers["follow"], "yes");
+        // The option, and what shows its step
+        assert_eq!(chain(3).config, "docker-logs");
+        assert_eq!(chain(3).answers["since"], "Last hour");
+        assert_eq!(chain(3).answers["follow"], "false");
+        assert!(chain(4).answers.is_empty());
+    }
+
+    #[test]
+    fn test_search_config_ranks_through_the_filter() {
+        let mut ls = load(
+            r#"{
+                "command": "ls",
+                "steps": [
+                    { "id": "all", "prompt": "Show hidden files?", "type": "toggle", "flag": "-a" },
+                    { "id": "long", "prompt": "Long listing format?", "type": "toggle", "flag": "-l" }
+                ]
+            }"#,
+        );
+        ls.name = "ls".to_string();
+        let logs = load(
+            r#"{
+                "command": "docker logs",
+                "steps": [
+                    { "id": "follow", "prompt": "Follow log output?", "type": "toggle", "flag": "-f" }
+                ],
+                "presets": [{ "label": "Tail 100 lines", "flags": "--tail 100" }]
+            }"#,
+        );
+        let configs = vec![ls, logs];
+        let config = search_config(&configs);
+        let labels: Vec<String> = config.steps[0]
+            .options
+            .iter()
+            .map(|o| o.label.clone())
+            .collect();
+        let (best, _) = crate::fuzzy::rank("follow", &labels)[0].clone();
+        assert!(labels[best].contains("docker-logs"), "{}", labels[best]);
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::config::ConfigError;
+use crate::shell_history::Shell;
+
+/// User preferences from `~/.config/i/settings.json`. Every setting is
+/// optional; a missing file means the defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub shell_history: ShellHistory,
+}
+
+/// Whether and where executed commands are added to the shell's history.
+/// `"shell_history": true` turns it on with the shell and file detected
+/// from the environment.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(from = "ShellHistoryDef")]
+pub struct ShellHistory {
+    pub enabled: bool,
+    /// Overrides the shell detected from `$SHELL`
+    pub shell: Option<Shell>,
+    /// Overrides `$HISTFILE` and the shell's default history file
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ShellHistoryDef {
+    Enabled(bool),
+    Detailed {
+        #[serde(default = "enabled_by_default")]
+        enabled: bool,
+        #[serde(default)]
+        shell: Option<Shell>,
+        #[serde(default)]
+        file: Option<PathBuf>,
+    },
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl From<ShellHistoryDef> for ShellHistory {
+    fn from(def: ShellHistoryDef) -> Self {
+        match def {
+            ShellHistoryDef::Enabled(enabled) => ShellHistory {
+                enabled,
+                ..ShellHistory::default()
+            },
+            ShellHistoryDef::Detailed {
+                enabled,
+                shell,
+                file,
+            } => ShellHistory {
+                enabled,
+                shell,
+                file,
+            },
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Result<Settings, ConfigError> {
+        match settings_path() {
+            Some(path) if path.exists() => {
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| ConfigError::ReadError(path.clone(), e.to_string()))?;
+                serde_json::from_str(&content)
+                    .map_err(|e| ConfigError::ParseError(path.clone(), e.to_string()))
+            }
+            _ => Ok(Settings::default()),
+        }
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("i").join("settings.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_settings() {
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert!(!settings.shell_history.enabled);
+    }
+
+    #[test]
+    fn test_shell_history_short_form() {
+        let settings: Settings = serde_json::from_str(r#"{ "shell_history": true }"#).unwrap();
+        assert_eq!(
+            settings.shell_history,
+            ShellHistory {
+                enabled: true,
+                shell: None,
+                file: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_shell_history_object_form() {
+        let json = r#"{ "shell_history": { "shell": "zsh", "file": "/tmp/hist" } }"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            settings.shell_history,
+            ShellHistory {
+                enabled: true,
+                shell: Some(Shell::Zsh),
+                file: Some(PathBuf::from("/tmp/hist")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_setting_is_rejected() {
+        assert!(serde_json::from_str::<Settings>(r#"{ "shell_histroy": true }"#).is_err());
+    }
+}
@@ -0,0 +1,446 @@
+use crate::config::Quote;
+
+/// Characters that never need quoting in a POSIX shell word
+fn is_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_-./:=@,+%^".contains(c)
+}
+
+/// Quote a value so `sh` reads it back as a single word, unchanged.
+/// Plain values are left as-is; anything else is wrapped in single quotes.
+pub fn quote(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(is_safe) {
+        return value.to_string();
+    }
+    single_quote(value)
+}
+
+fn single_quote(value: &str) -> String {
+    format!("'{}'", escape_single(value))
+}
+
+fn double_quote(value: &str) -> String {
+    format!("\"{}\"", escape_double(value))
+}
+
+/// Escape for use between single quotes: nothing is special there except
+/// the closing quote itself
+fn escape_single(value: &str) -> String {
+    value.replace('\'', r"'\''")
+}
+
+/// Escape for use between double quotes, so `$`, backticks and backslashes
+/// are taken literally
+fn escape_double(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Like `quote`, but keeps a leading `~` and glob characters working, since
+/// typed paths like `~/.ssh/id_rsa` or `*.log` are meant to be expanded
+fn quote_auto(value: &str) -> String {
+    let expandable = value
+        .char_indices()
+        .all(|(i, c)| is_safe(c) || "*?[]".contains(c) || (c == '~' && i == 0));
+    if !value.is_empty() && expandable {
+        return value.to_string();
+    }
+    quote(value)
+}
+
+/// The word `sh` makes of a value `quote_as(value, Quote::Auto)` gives: a
+/// leading `~` becomes the home directory. `None` for globs and `~user`,
+/// which only a shell can expand.
+pub fn expand_auto(value: &str) -> Option<String> {
+    if quote_auto(value) != value {
+        return Some(value.to_string());
+    }
+    if value.contains(['*', '?', '[', ']']) {
+        return None;
+    }
+    match value.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            Some(format!("{}{}", dirs::home_dir()?.display(), rest))
+        }
+        Some(_) => None,
+        None => Some(value.to_string()),
+    }
+}
+
+/// Quote a text step's value the way its config asks for
+pub fn quote_as(value: &str, mode: Quote) -> String {
+    match mode {
+        Quote::Auto => quote_auto(value),
+        Quote::None => value.to_string(),
+        Quote::Single => single_quote(value),
+        Quote::Double => double_quote(value),
+    }
+}
+
+/// The quoting in effect at some point of a command line
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Context {
+    #[default]
+    Unquoted,
+    Single,
+    Double,
+}
+
+/// Follows quotes and backslash escapes while reading a command line left to right
+#[derive(Debug, Default)]
+pub struct Scanner {
+    context: Context,
+    escaped: bool,
+}
+
+impl Scanner {
+    pub fn context(&self) -> Context {
+        self.context
+    }
+
+    /// Whether the last character was an escaping backslash
+    pub fn escaped(&self) -> bool {
+        self.escaped
+    }
+
+    pub fn advance(&mut self, c: char) {
+        if self.escaped {
+            self.escaped = false;
+            return;
+        }
+        self.context = match (self.context, c) {
+            (Context::Unquoted | Context::Double, '\\') => {
+                self.escaped = true;
+                self.context
+            }
+            (Context::Unquoted, '\'') => Context::Single,
+            (Context::Unquoted, '"') => Context::Double,
+            (Context::Single, '\'') | (Context::Double, '"') => Context::Unquoted,
+            (context, _) => context,
+        };
+    }
+}
+
+/// Escape `value` for insertion at a point of a command line with the given
+/// quoting, so it stays a literal part of the same word
+pub fn escape_in(value: &str, context: Context) -> String {
+    match context {
+        Context::Unquoted => quote(value),
+        Context::Single => escape_single(value),
+        Context::Double => escape_double(value),
+    }
+}
+
+/// Replace every `placeholder` in `template` with `value`, escaped for the
+/// quotes around each occurrence: `'<pattern>'` gets the value escaped for
+/// single quotes, a bare `<pod>` gets it quoted if needed
+pub fn substitute(template: &str, placeholder: &str, value: &str) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut scanner = Scanner::default();
+    let mut rest = template;
+
+    while let Some(c) = rest.chars().next() {
+        if !placeholder.is_empty() && rest.starts_with(placeholder) {
+            result.push_str(&escape_in(value, scanner.context()));
+            rest = &rest[placeholder.len()..];
+            continue;
+        }
+        result.push(c);
+        scanner.advance(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    result
+}
+
+/// Unquoted characters that only mean something to a shell: pipes,
+/// redirects, expansions, globs and grouping
+const SHELL_SYNTAX: &str = "|&;<>()$`*?[{}";
+
+/// Split a command line into words the way `sh` would, for running it
+/// without a shell. Returns `None` if the command needs the shell for more
+/// than word splitting and quote removal, e.g. `$(pwd)`, a pipe, a glob or
+/// a leading `~`.
+pub fn split(command: &str) -> Option<Vec<String>> {
+    split_words(command, true)
+}
+
+/// Like `split`, for words that follow the program name, where `NAME=value`
+/// is a plain word
+pub fn split_args(text: &str) -> Option<Vec<String>> {
+    split_words(text, false)
+}
+
+fn split_words(command: &str, at_start: bool) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            '\n' => {}
+                            c => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        '$' | '`' => return None,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => match chars.next()? {
+                '\n' => {}
+                c => {
+                    in_word = true;
+                    word.push(c);
+                }
+            },
+            // Home directory, comment
+            '~' | '#' if !in_word => return None,
+            // `NAME=value cmd` sets a variable for the command
+            '=' if at_start && words.is_empty() => return None,
+            c if SHELL_SYNTAX.contains(c) => return None,
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_plain_value_unchanged() {
+        assert_eq!(quote("kube-system"), "kube-system");
+        assert_eq!(quote("nginx:1.25"), "nginx:1.25");
+    }
+
+    #[test]
+    fn test_quote_empty_value() {
+        assert_eq!(quote(""), "''");
+    }
+
+    #[test]
+    fn test_quote_spaces_and_metacharacters() {
+        assert_eq!(quote("my pod"), "'my pod'");
+        assert_eq!(quote("$(rm -rf ~)"), "'$(rm -rf ~)'");
+        assert_eq!(quote("a;b|c&d"), "'a;b|c&d'");
+    }
+
+    #[test]
+    fn test_quote_single_quote() {
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn test_quote_round_trips_through_sh() {
+        for value in ["it's", "$(echo hi)", "`id`", "a b\tc", "\"x\"", "*"] {
+            let output = std::process::Command::new("sh")
+                .args(["-c", &format!("printf %s {}", quote(value))])
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), value);
+        }
+    }
+
+    const HOSTILE: [&str; 8] = [
+        "it's",
+        "$(echo pwned)",
+        "`id`",
+        "'; echo pwned; '",
+        "\"; echo pwned; \"",
+        "a\\",
+        "${HOME}",
+        "line\nbreak",
+    ];
+
+    /// What `sh` prints for `printf %s <command>`
+    fn sh_printf(command: &str) -> String {
+        let output = std::process::Command::new("sh")
+            .args(["-c", &format!("printf %s {}", command)])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    #[test]
+    fn test_quote_as_modes() {
+        assert_eq!(quote_as("main", Quote::Auto), "main");
+        assert_eq!(quote_as("a b", Quote::Auto), "'a b'");
+        assert_eq!(quote_as("~/.ssh/id_rsa", Quote::Auto), "~/.ssh/id_rsa");
+        assert_eq!(quote_as("logs/*.log", Quote::Auto), "logs/*.log");
+        assert_eq!(quote_as("a~b", Quote::Auto), "'a~b'");
+        assert_eq!(quote_as("*.rs src", Quote::None), "*.rs src");
+        assert_eq!(quote_as("main", Quote::Single), "'main'");
+        assert_eq!(quote_as("say \"hi\"", Quote::Double), r#""say \"hi\"""#);
+    }
+
+    #[test]
+    fn test_quote_as_hostile_values_stay_literal() {
+        for mode in [Quote::Auto, Quote::Single, Quote::Double] {
+            for value in HOSTILE {
+                assert_eq!(sh_printf(&quote_as(value, mode)), value, "{:?}", mode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scanner_tracks_quotes() {
+        let mut scanner = Scanner::default();
+        let mut contexts = Vec::new();
+        for c in r#"a'b'"c\"d"\'"#.chars() {
+            scanner.advance(c);
+            contexts.push(scanner.context());
+        }
+        use Context::*;
+        assert_eq!(
+            contexts,
+            vec![
+                Unquoted, Single, Single, Unquoted, Double, Double, Double, Double, Double,
+                Unquoted, Unquoted, Unquoted
+            ]
+        );
+    }
+
+    #[test]
+    fn test_substitute_respects_surrounding_quotes() {
+        assert_eq!(substitute("-name '<p>'", "<p>", "it's"), r"-name 'it'\''s'");
+        assert_eq!(substitute("-m \"<p>\"", "<p>", "$HOME"), r#"-m "\$HOME""#);
+        assert_eq!(
+            substitute("-n <p> -l <p>", "<p>", "a b"),
+            "-n 'a b' -l 'a b'"
+        );
+        assert_eq!(substitute("-n <p>", "<p>", "web"), "-n web");
+    }
+
+    #[test]
+    fn test_substitute_hostile_values_stay_literal() {
+        // (template, what sh should print around the value)
+        let templates = [
+            ("<v>", "", ""),
+            ("'<v>'", "", ""),
+            ("\"<v>\"", "", ""),
+            ("x'<v>'y", "x", "y"),
+            ("\"pre <v> post\"", "pre ", " post"),
+        ];
+        for (template, before, after) in templates {
+            for value in HOSTILE {
+                let command = substitute(template, "<v>", value);
+                assert_eq!(
+                    sh_printf(&command),
+                    format!("{}{}{}", before, value, after),
+                    "{}",
+                    command
+                );
+            }
+        }
+    }
+
+    fn words(words: &[&str]) -> Option<Vec<String>> {
+        Some(words.iter().map(|w| w.to_string()).collect())
+    }
+
+    #[test]
+    fn test_split_words_and_quotes() {
+        assert_eq!(
+            split("git commit -m 'fix the bug'"),
+            words(&["git", "commit", "-m", "fix the bug"])
+        );
+        assert_eq!(
+            split(r#"echo "a \"b\" \$c" x\ y '' "#),
+            words(&["echo", r#"a "b" $c"#, "x y", ""])
+        );
+        assert_eq!(split("a'b'\"c\"d"), words(&["abcd"]));
+        assert_eq!(split("  "), words(&[]));
+    }
+
+    #[test]
+    fn test_split_needs_shell() {
+        for command in [
+            "echo $(pwd)",
+            "echo \"$HOME\"",
+            "ls | wc -l",
+            "make && make install",
+            "cat > out.txt",
+            "ls *.log",
+            "ls ~/src",
+            "FOO=1 cargo test",
+            "echo 'unterminated",
+        ] {
+            assert_eq!(split(command), None, "{}", command);
+        }
+        // Quoted, escaped or mid-word, the same characters are plain text
+        assert!(split("grep -e '$(x)|*' a~b key=value \\*").is_some());
+    }
+
+    #[test]
+    fn test_split_args_takes_assignments_as_words() {
+        assert_eq!(split_args("FOO=1 --x"), words(&["FOO=1", "--x"]));
+        assert_eq!(split_args("-n <pod>"), None);
+    }
+
+    #[test]
+    fn test_expand_auto() {
+        let home = dirs::home_dir().unwrap().display().to_string();
+        assert_eq!(
+            expand_auto("~/.ssh/id_rsa"),
+            Some(format!("{}/.ssh/id_rsa", home))
+        );
+        assert_eq!(expand_auto("~"), Some(home));
+        assert_eq!(expand_auto("a b"), Some("a b".to_string()));
+        assert_eq!(expand_auto("'*'"), Some("'*'".to_string()));
+        assert_eq!(expand_auto("logs/*.log"), None);
+        assert_eq!(expand_auto("~root"), None);
+    }
+
+    #[test]
+    fn test_split_matches_sh_for_quoted_values() {
+        for value in HOSTILE {
+            for quoted in [quote(value), quote_as(value, Quote::Double)] {
+                assert_eq!(
+                    split(&format!("printf %s {}", quoted)),
+                    words(&["printf", "%s", value]),
+                    "{}",
+                    quoted
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+use crate::settings::ShellHistory;
+use crate::shell::{Context, Scanner};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// The shell a path like `/bin/zsh` or `~/.zsh_history` belongs to
+    fn from_path(path: &str) -> Option<Shell> {
+        let name = Path::new(path).file_name()?.to_str()?;
+        [Shell::Zsh, Shell::Fish, Shell::Bash]
+            .into_iter()
+            .find(|shell| name.contains(shell.name()))
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+
+    /// Where the shell keeps its history when `$HISTFILE` isn't set
+    fn default_file(self) -> Option<PathBuf> {
+        let home = dirs::home_dir()?;
+        Some(match self {
+            Shell::Bash => home.join(".bash_history"),
+            Shell::Zsh => env::var_os("ZDOTDIR")
+                .map(PathBuf::from)
+                .unwrap_or(home)
+                .join(".zsh_history"),
+            Shell::Fish => env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".local").join("share"))
+                .join("fish")
+                .join("fish_history"),
+        })
+    }
+}
+
+/// Add `command` to the user's shell history, if their settings ask for it.
+/// The shell comes from the settings, `$SHELL` or `$HISTFILE`, in that order.
+pub fn append(settings: &ShellHistory, command: &str) -> io::Result<()> {
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    let histfile = env::var("HISTFILE").ok().filter(|file| !file.is_empty());
+    let shell = settings
+        .shell
+        .or_else(|| env::var("SHELL").ok().and_then(|s| Shell::from_path(&s)))
+        .or_else(|| histfile.as_deref().and_then(Shell::from_path))
+        .ok_or_else(|| io::Error::other("unknown shell; set \"shell\" in shell_history"))?;
+
+    // fish ignores $HISTFILE
+    let file = settings
+        .file
+        .as_deref()
+        .map(expand_home)
+        .or_else(|| histfile.filter(|_| shell != Shell::Fish).map(PathBuf::from))
+        .or_else(|| shell.default_file())
+        .ok_or_else(|| io::Error::other("no history file; set \"file\" in shell_history"))?;
+
+    let mut out = OpenOptions::new().create(true).append(true).open(&file)?;
+    out.write_all(&entry(shell, command, now()))
+}
+
+/// `command` as a line (or record) of `shell`'s history file
+fn entry(shell: Shell, command: &str, when: u64) -> Vec<u8> {
+    match shell {
+        Shell::Bash => format!("{}\n", one_line(command)).into_bytes(),
+        // Extended format: `: <start>:<elapsed>;<command>`, with a backslash
+        // before each newline inside the command
+        Shell::Zsh => {
+            metafy(format!(": {}:0;{}\n", when, command.replace('\n', "\\\n")).as_bytes())
+        }
+        Shell::Fish => format!(
+            "- cmd: {}\n  when: {}\n",
+            command.replace('\\', "\\\\").replace('\n', "\\n"),
+            when
+        )
+        .into_bytes(),
+    }
+}
+
+/// `command` on one line for bash, which reads each line of its history file
+/// as a command. A backslash-newline is dropped, a newline between commands
+/// becomes `; ` (or a space after `|`, `&&`, `do` and the like), and one
+/// inside quotes becomes `$'\n'`.
+fn one_line(command: &str) -> String {
+    let mut scanner = Scanner::default();
+    let mut line = String::with_capacity(command.len());
+    // After a newline between commands, the next line's indent is dropped
+    let mut indent = false;
+    for c in command.chars() {
+        if indent && (c == ' ' || c == '\t') {
+            continue;
+        }
+        indent = false;
+        if c != '\n' {
+            scanner.advance(c);
+            line.push(c);
+            continue;
+        }
+        if scanner.escaped() {
+            line.pop();
+        } else {
+            match scanner.context() {
+                Context::Unquoted => {
+                    let before = line.trim_end();
+                    let continues = before.is_empty()
+                        || before.ends_with(['|', '&', ';', '(', '{'])
+                        || ["do", "then", "else"]
+                            .iter()
+                            .any(|word| before.split_whitespace().last() == Some(word));
+                    line.truncate(before.len());
+                    line.push_str(if continues { " " } else { "; " });
+                    indent = true;
+                }
+                Context::Single => line.push_str("'$'\\n''"),
+                Context::Double => line.push_str("\"$'\\n'\""),
+            }
+        }
+        scanner.advance(c);
+    }
+    line
+}
+
+/// zsh stores bytes it uses internally (NUL and 0x83..=0xa2, which show up
+/// in UTF-8 text) as 0x83 followed by the byte xor 32
+fn metafy(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for &byte in bytes {
+        if byte == 0 || (0x83..=0xa2).contains(&byte) {
+            out.push(0x83);
+            out.push(byte ^ 32);
+        } else {
+            out.push(byte);
+        }
+    }
+    out
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_from_path() {
+        assert_eq!(Shell::from_path("/bin/zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_path("/usr/local/bin/fish"), Some(Shell::Fish));
+        assert_eq!(
+            Shell::from_path("/home/me/.bash_history"),
+            Some(Shell::Bash)
+        );
+        assert_eq!(Shell::from_path("/bin/sh"), None);
+    }
+
+    #[test]
+    fn test_bash_entry() {
+        assert_eq!(entry(Shell::Bash, "ls -la", 1), b"ls -la\n");
+    }
+
+    #[test]
+    fn test_bash_entry_folds_newlines() {
+        let bash = |command| String::from_utf8(entry(Shell::Bash, command, 1)).unwrap();
+        assert_eq!(bash("cd /tmp\nls -la"), "cd /tmp; ls -la\n");
+        assert_eq!(bash("make \\\n  install"), "make   install\n");
+        assert_eq!(bash("ls |\n  wc -l"), "ls | wc -l\n");
+        assert_eq!(
+            bash("for f in *; do\n  echo $f\ndone"),
+            "for f in *; do echo $f; done\n"
+        );
+        assert_eq!(
+            bash("echo 'a\nb' \"c\nd\""),
+            "echo 'a'$'\\n''b' \"c\"$'\\n'\"d\"\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bash_entry_keeps_quoted_newlines() {
+        let command = "printf '%s|' 'a\nb' \"c\nd\"";
+        let line = String::from_utf8(entry(Shell::Bash, command, 1)).unwrap();
+        let run = |command: &str| {
+            let output = std::process::Command::new("bash")
+                .args(["-c", command])
+                .output();
+            output.map(|o| o.stdout)
+        };
+        if let Ok(expected) = run(command) {
+            assert_eq!(run(line.trim_end()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_zsh_entry_escapes_newlines() {
+        assert_eq!(
+            entry(Shell::Zsh, "cd /tmp\nls", 1),
+            b": 1:0;cd /tmp\\\nls\n"
+        );
+    }
+
+    #[test]
+    fn test_zsh_extended_entry() {
+        assert_eq!(
+            entry(Shell::Zsh, "git commit -m 'fix'", 1700000000),
+            b": 1700000000:0;git commit -m 'fix'\n"
+        );
+        // U+2192 is e2 86 92; the last two bytes get metafied
+        assert_eq!(
+            entry(Shell::Zsh, "echo \u{2192}", 5),
+            b": 5:0;echo \xe2\x83\xa6\x83\xb2\n"
+        );
+    }
+
+    #[test]
+    fn test_fish_entry() {
+        assert_eq!(
+            String::from_utf8(entry(Shell::Fish, r"echo a\b", 1700000000)).unwrap(),
+            "- cmd: echo a\\\\b\n  when: 1700000000\n"
+        );
+    }
+
+    #[test]
+    fn test_append_to_configured_file() {
+        let file = env::temp_dir().join(format!("i-history-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&file);
+        let settings = ShellHistory {
+            enabled: true,
+            shell: Some(Shell::Bash),
+            file: Some(file.clone()),
+        };
+        append(&settings, "ls").unwrap();
+        append(&settings, "ls -la").unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "ls\nls -la\n");
+
+        // Nothing is written unless enabled
+        let disabled = ShellHistory {
+            enabled: false,
+            ..settings
+        };
+        append(&disabled, "rm -rf /").unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "ls\nls -la\n");
+        let _ = std::fs::remove_file(&file);
+    }
+}
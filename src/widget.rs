@@ -0,0 +1,85 @@
+use crate::shell_history::Shell;
+
+// Each script defines a widget bound to Ctrl-X i. It runs `i` on the
+// current command line, with the chosen command written to a temp file
+// instead of being run, and puts that command on the line for editing.
+
+const ZSH: &str = r#"_i_widget() {
+  local tmp
+  tmp=$(mktemp "${TMPDIR:-/tmp}/i.XXXXXX") || return
+  i --buffer "$BUFFER" --output-file "$tmp" </dev/tty
+  if [[ -s $tmp ]]; then
+    BUFFER=$(<"$tmp")
+    CURSOR=${#BUFFER}
+  fi
+  rm -f "$tmp"
+  zle reset-prompt
+}
+zle -N _i_widget
+bindkey '^Xi' _i_widget
+"#;
+
+const BASH: &str = r#"_i_widget() {
+  local tmp
+  tmp=$(mktemp "${TMPDIR:-/tmp}/i.XXXXXX") || return
+  i --buffer "$READLINE_LINE" --output-file "$tmp" </dev/tty
+  if [[ -s $tmp ]]; then
+    READLINE_LINE=$(<"$tmp")
+    READLINE_POINT=${#READLINE_LINE}
+  fi
+  rm -f "$tmp"
+}
+bind -x '"\C-xi": _i_widget'
+"#;
+
+const FISH: &str = r#"function _i_widget
+    set -l tmp (mktemp)
+    or return
+    i --buffer (commandline) --output-file $tmp </dev/tty
+    if test -s $tmp
+        commandline --replace -- (string collect <$tmp)
+        commandline --cursor (string length -- (commandline))
+    end
+    rm -f $tmp
+    commandline --function repaint
+end
+bind \cxi _i_widget
+"#;
+
+/// The shell code printed by `i --init <shell>`
+pub fn script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => BASH,
+        Shell::Zsh => ZSH,
+        Shell::Fish => FISH,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripts_pass_buffer_and_output_file() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = script(shell);
+            assert!(script.contains("--buffer"), "{:?}", shell);
+            assert!(script.contains("--output-file"), "{:?}", shell);
+        }
+    }
+
+    #[test]
+    fn test_bash_script_parses() {
+        let Ok(output) = std::process::Command::new("bash")
+            .args(["-n", "-c", BASH])
+            .output()
+        else {
+            return; // No bash to check with
+        };
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
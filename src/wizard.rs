@@ -1,32 +1,173 @@
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, stdout};
+use std::time::Duration;
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEvent, KeyModifiers,
+    },
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Paragraph, Wrap},
 };
+use serde::{Deserialize, Serialize};
 
-use crate::config::{Answer, Config, Step, StepType};
-use crate::output::OutputMode;
+use crate::cache::{self, FetchCache};
+use crate::config::{self, Answer, Chain, Config, PlaceholderOption, Preset, Step, StepType};
+use crate::fetch::{self, FetchOutcome, PendingFetch};
+use crate::fuzzy;
+use crate::history::{self, History};
+use crate::line_editor::LineEditor;
+use crate::output::{self, CommandLine, OutputMode, Part};
+use crate::shell;
 
 pub enum WizardResult {
-    Command(String, OutputMode),
-    Chain(String), // Chain to another config
-    Back,          // Go back to previous wizard in chain
+    Command(CommandLine, OutputMode),
+    Chain(Chain), // Chain to another config
+    Back,         // Go back to previous wizard in chain
+    Search,       // Search every config
     Quit,
 }
 
+/// A value given for a step outside the TUI. `--answer` gives one string,
+/// which a multi step splits on commas; an answers file can give a list, as
+/// the history does for multi steps.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AnswerValue {
+    One(String),
+    List(Vec<String>),
+}
+
+impl From<&str> for AnswerValue {
+    fn from(value: &str) -> Self {
+        AnswerValue::One(value.to_string())
+    }
+}
+
+/// Problems with answers supplied on the command line or in an answers file
+#[derive(Debug, PartialEq)]
+pub enum AnswerError {
+    UnknownStep(String),
+    UnknownOption {
+        step: String,
+        label: String,
+        options: Vec<String>,
+    },
+    InvalidToggle(String, String),
+    NotMulti(String),
+    HiddenStep(String, String),
+    Chain(String, String, String),
+}
+
+impl std::fmt::Display for AnswerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnswerError::UnknownStep(id) => write!(f, "Unknown step id '{}'", id),
+            AnswerError::UnknownOption {
+                step,
+                label,
+                options,
+            } => {
+                writeln!(f, "Step '{}' has no option '{}'", step, label)?;
+                write!(f, "Valid options: {}", options.join(", "))
+            }
+            AnswerError::InvalidToggle(step, value) => write!(
+                f,
+                "Step '{}' is a toggle; expected true or false, got '{}'",
+                step, value
+            ),
+            AnswerError::NotMulti(step) => {
+                write!(f, "Step '{}' takes a single value, not a list", step)
+            }
+            AnswerError::HiddenStep(step, condition) => write!(
+                f,
+                "Step '{}' is hidden by its `when` condition ({})",
+                step, condition
+            ),
+            AnswerError::Chain(step, label, chain) => write!(
+                f,
+                "Option '{}' of step '{}' chains to '{}'; run that config directly",
+                label, step, chain
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AnswerError {}
+
+/// Scroll position of the list on screen. Kept across frames so moving the
+/// cursor only scrolls once it would leave the visible rows; updated while
+/// drawing, since that's when the available height is known.
+#[derive(Default)]
+struct Viewport {
+    offset: Cell<usize>,
+    rows: Cell<usize>, // Item rows shown in the last frame
+}
+
+impl Viewport {
+    /// The part of `lines` that fits in `height` rows, scrolled so line
+    /// `cursor` is visible, with "↑/↓ N more" rows when it doesn't all fit
+    fn show<'a>(&self, lines: Vec<Line<'a>>, cursor: usize, height: usize) -> Vec<Line<'a>> {
+        if lines.len() <= height {
+            self.offset.set(0);
+            self.rows.set(height);
+            return lines;
+        }
+
+        // Too short for indicators; just keep the cursor in view
+        let indicators = height >= 3;
+        let rows = if indicators {
+            height - 2
+        } else {
+            height.max(1)
+        };
+        let mut offset = self.offset.get().min(cursor);
+        if cursor >= offset + rows {
+            offset = cursor + 1 - rows;
+        }
+        // The terminal may have grown since the last frame
+        offset = offset.min(lines.len() - rows);
+        self.offset.set(offset);
+        self.rows.set(rows);
+
+        let above = offset;
+        let below = lines.len() - offset - rows;
+        let mut visible: Vec<Line> = lines.into_iter().skip(offset).take(rows).collect();
+        if indicators {
+            visible.insert(0, more_line("↑", above));
+            visible.push(more_line("↓", below));
+        }
+        visible
+    }
+
+    fn reset(&self) {
+        self.offset.set(0);
+    }
+}
+
+fn more_line(arrow: &str, count: usize) -> Line<'static> {
+    if count == 0 {
+        return Line::from("");
+    }
+    Line::from(Span::styled(
+        format!("  {} {} more", arrow, count),
+        Style::default().fg(Color::DarkGray),
+    ))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Phase {
     Menu,        // Initial menu: wizard vs presets
     Steps,       // Step-by-step wizard
     PresetInput, // Filling in preset placeholders
     Confirm,     // Final confirmation
+    Review,      // Answers so far, to jump back to any step
+    SavePreset,  // Naming the wizard's answers as a new preset
 }
 
 pub struct Wizard {
@@ -35,21 +176,42 @@ pub struct Wizard {
     answers: HashMap<String, Answer>,
     current_step: usize,
     phase: Phase,
-    // Menu state: 0 = wizard, 1+ = presets
+    // Menu state: 0 = wizard, then recent commands, then presets
     menu_index: usize,
+    // Commands built with this config before, pinned and most recent first
+    history: History,
+    history_entries: Vec<history::Entry>,
+    // Review state: highlighted visible step, and whether a step is being
+    // edited from the review screen
+    review_index: usize,
+    reviewing: bool,
     // State for current widget
     choice_index: usize,
     toggle_value: bool,
-    text_buffer: String,
+    text_buffer: LineEditor,
     multi_selected: Vec<bool>,
+    // Type-to-filter query for the current list, while filtering
+    filter: Option<String>,
+    viewport: Viewport,
     // Dynamic placeholder options (e.g., container selection)
     placeholder_values: Vec<(String, String)>, // (name, id) pairs
     placeholder_index: usize,
     active_placeholder: Option<String>, // The placeholder key being resolved
+    placeholder_queue: Vec<String>,     // Placeholders to pick, in resolution order
+    resolved_placeholders: HashMap<String, String>, // Picked values
+    cache: FetchCache,                  // Fetch results, shared across wizards in a session
+    fetch: Option<PendingFetch>,        // Fetch running in the background
+    fetch_error: Vec<String>,           // Summary + stderr of the last failed fetch
+    restore_pick: Option<String>,       // Value to re-highlight once options load
     // Preset user-input placeholders (e.g., <url>, <data>)
     preset_placeholders: Vec<String>, // List of placeholders to fill
     preset_placeholder_values: HashMap<String, String>, // Filled values
     preset_placeholder_index: usize,  // Current placeholder being edited
+    // Saving the answers as a preset: highlighted text step, and the text
+    // steps whose value is asked for each time instead of kept
+    save_index: usize,
+    save_placeholders: HashSet<String>,
+    notice: Option<String>, // Shown in Confirm, e.g. where a preset was saved
     // Whether the CLI tool is found in PATH
     command_found: bool,
 }
@@ -85,20 +247,142 @@ impl Wizard {
             current_step: 0,
             phase,
             menu_index: 0,
+            history: History::new(None),
+            history_entries: Vec::new(),
+            review_index: 0,
+            reviewing: false,
             choice_index: 0,
             toggle_value: false,
-            text_buffer: String::new(),
+            text_buffer: LineEditor::default(),
             multi_selected: Vec::new(),
+            filter: None,
+            viewport: Viewport::default(),
             placeholder_values: Vec::new(),
             placeholder_index: 0,
             active_placeholder: None,
+            placeholder_queue: Vec::new(),
+            resolved_placeholders: HashMap::new(),
+            cache: FetchCache::new(None),
+            fetch: None,
+            fetch_error: Vec::new(),
+            restore_pick: None,
             preset_placeholders: Vec::new(),
             preset_placeholder_values: HashMap::new(),
             preset_placeholder_index: 0,
+            save_index: 0,
+            save_placeholders: HashSet::new(),
+            notice: None,
             command_found,
         }
     }
 
+    /// A wizard ready to run, with the config's past commands loaded
+    pub fn open(config: Config, base_command: Vec<String>, history: &History) -> Self {
+        let mut wizard = Self::new(config, base_command);
+        wizard.load_history(history.clone());
+        wizard.init_step();
+        wizard
+    }
+
+    /// The wizard for a config this one chains to, with the flags and
+    /// answers the chain passes on
+    pub fn open_chain(&self, chain: &Chain, config: Config, history: &History) -> Self {
+        let words = chain
+            .command_words()
+            .unwrap_or_else(|| config.command_words());
+        // Right after this wizard's command if the chained one extends it,
+        // e.g. `kubectl` + `-n prod` + `logs`, keeping the flags this one was
+        // given; otherwise at the end
+        let command = self.config.command_words();
+        let base_command = if words.starts_with(&command) {
+            let mut base_command = self.base_command.clone();
+            base_command.extend(self.chain_flags(chain));
+            base_command.extend_from_slice(&words[command.len()..]);
+            base_command
+        } else {
+            let mut base_command = words;
+            base_command.extend(self.chain_flags(chain));
+            base_command
+        };
+
+        let mut wizard = Self::new(config, base_command);
+        let answers: Vec<(String, String)> = chain
+            .answers
+            .iter()
+            .map(|(id, value)| (id.clone(), self.expand_references(value)))
+            .collect();
+        wizard.seed_answers(&answers);
+        wizard.load_history(history.clone());
+        if let Some(label) = &chain.preset {
+            wizard.select_preset(label);
+        }
+        wizard.init_step();
+        wizard
+    }
+
+    /// Start from the menu with the preset called `label` highlighted
+    fn select_preset(&mut self, label: &str) {
+        if let Some(i) = self.config.presets.iter().position(|p| p.label == label) {
+            self.phase = Phase::Menu;
+            self.menu_index = 1 + self.recent().len() + i;
+        }
+    }
+
+    /// Open with the list already filtered by `query`, as typed after `/`
+    pub fn start_filter(&mut self, query: &str) {
+        self.set_filter(query.to_string());
+    }
+
+    /// The words of the flags a chain carries over, in config order. Shell
+    /// text that can't be split into words is carried as one.
+    fn chain_flags(&self, chain: &Chain) -> Vec<String> {
+        self.config
+            .steps
+            .iter()
+            .filter(|step| chain.flags.contains(&step.id) && self.should_show_step(step))
+            .filter_map(|step| Some(self.step_flags(step, self.answers.get(&step.id)?)))
+            .flatten()
+            .flat_map(|part| {
+                part.args()
+                    .unwrap_or_else(|| vec![part.shown().to_string()])
+            })
+            .collect()
+    }
+
+    /// Fill `{{step_id}}` references with this wizard's answers, as typed
+    fn expand_references(&self, text: &str) -> String {
+        let mut expanded = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let inner = &rest[start + 2..];
+            let Some(end) = inner.find("}}") else {
+                break;
+            };
+            expanded.push_str(&rest[..start]);
+            match self.reference_value(inner[..end].trim()) {
+                Some(value) => expanded.push_str(&value),
+                None => expanded.push_str(&rest[start..start + end + 4]),
+            }
+            rest = &inner[end + 2..];
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+
+    /// Pre-select answers for the steps they name. Unlike `apply_answers`,
+    /// the steps are still asked, and values that don't fit a step are
+    /// skipped rather than stopping the wizard.
+    fn seed_answers(&mut self, values: &[(String, String)]) {
+        for (id, value) in values {
+            let Some(step) = self.config.steps.iter().find(|s| &s.id == id) else {
+                continue;
+            };
+            if let Ok(answer) = parse_answer(step, &AnswerValue::from(value.as_str())) {
+                self.answers.insert(id.clone(), answer);
+            }
+        }
+    }
+
     /// Extract user-input placeholders (e.g., `<url>`, `<data>`) from preset flags.
     /// Returns a deduplicated list preserving order of first occurrence.
     fn extract_preset_placeholders(flags: &str) -> Vec<String> {
@@ -122,10 +406,15 @@ impl Wizard {
 
     fn prepare_preset_input(&mut self) {
         if let Some(preset) = self.selected_preset() {
-            self.preset_placeholders = Self::extract_preset_placeholders(&preset.flags);
+            // Placeholders with configured options are picked in Confirm instead
+            self.preset_placeholders = Self::extract_preset_placeholders(&preset.flags)
+                .into_iter()
+                .filter(|p| !self.config.placeholder_options.contains_key(p))
+                .collect();
             self.preset_placeholder_values.clear();
             self.preset_placeholder_index = 0;
             self.text_buffer.clear();
+            self.filter = None;
         }
     }
 
@@ -136,7 +425,7 @@ impl Wizard {
     fn save_preset_placeholder(&mut self) {
         if let Some(placeholder) = self.current_preset_placeholder().cloned() {
             self.preset_placeholder_values
-                .insert(placeholder, self.text_buffer.clone());
+                .insert(placeholder, self.text_buffer.as_str().to_string());
         }
     }
 
@@ -155,11 +444,12 @@ impl Wizard {
         self.save_preset_placeholder();
         if self.preset_placeholder_index > 0 {
             self.preset_placeholder_index -= 1;
-            self.text_buffer = self
+            let value = self
                 .current_preset_placeholder()
                 .and_then(|p| self.preset_placeholder_values.get(p))
                 .cloned()
                 .unwrap_or_default();
+            self.text_buffer.set(&value);
             false // Stay in PresetInput
         } else {
             true // Go back to Menu
@@ -167,36 +457,247 @@ impl Wizard {
     }
 
     fn menu_item_count(&self) -> usize {
-        1 + self.config.presets.len() // wizard + presets
+        1 + self.recent().len() + self.config.presets.len() // wizard + recent + presets
+    }
+
+    /// Whether there's a menu to start from, rather than the first step
+    fn has_menu(&self) -> bool {
+        !self.config.presets.is_empty() || !self.history_entries.is_empty()
+    }
+
+    /// Past commands shown in the menu's Recent section
+    fn recent(&self) -> &[history::Entry] {
+        &self.history_entries[..history::shown(&self.history_entries)]
+    }
+
+    fn selected_recent(&self) -> Option<&history::Entry> {
+        self.menu_index
+            .checked_sub(1)
+            .and_then(|i| self.recent().get(i))
+    }
+
+    fn selected_preset(&self) -> Option<&Preset> {
+        self.menu_index
+            .checked_sub(1 + self.recent().len())
+            .and_then(|i| self.config.presets.get(i))
+    }
+
+    /// The config's file name, which its history and presets are saved by
+    fn config_name(&self) -> String {
+        self.config.name.clone()
+    }
+
+    /// Load past commands, and start from the menu if there are any
+    fn load_history(&mut self, history: History) {
+        self.history_entries = history.load(&self.config_name());
+        self.history = history;
+        if self.has_menu() && self.phase == Phase::Steps {
+            self.phase = Phase::Menu;
+        }
     }
 
-    fn selected_preset(&self) -> Option<&crate::config::Preset> {
-        if self.menu_index > 0 {
-            self.config.presets.get(self.menu_index - 1)
+    /// Remember the command about to be returned, with the wizard's answers
+    /// when it was built step by step
+    fn record_history(&mut self, command: &CommandLine) -> io::Result<()> {
+        let answers = if self.menu_index == 0 {
+            self.saved_answers()
         } else {
-            None
+            Vec::new()
+        };
+        history::record(&mut self.history_entries, command, answers);
+        self.history
+            .save(&self.config_name(), &self.history_entries)
+    }
+
+    /// Answers of the visible steps in `--answer` form, with a multi step's
+    /// labels kept as a list so commas in them survive
+    fn saved_answers(&self) -> Vec<(String, AnswerValue)> {
+        self.config
+            .steps
+            .iter()
+            .filter_map(|step| {
+                let value = match self.answers.get(&step.id) {
+                    Some(Answer::Multi(indices)) if self.should_show_step(step) => {
+                        AnswerValue::List(
+                            indices
+                                .iter()
+                                .filter_map(|i| step.options.get(*i).map(|o| o.label.clone()))
+                                .collect(),
+                        )
+                    }
+                    _ => AnswerValue::One(self.answer_value(step)?),
+                };
+                Some((step.id.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Go to Confirm with the highlighted recent command. One built in the
+    /// wizard gets its answers back, so it can be edited like a fresh run.
+    fn open_recent(&mut self) {
+        let Some(entry) = self.selected_recent().cloned() else {
+            return;
+        };
+        self.preset_placeholders.clear();
+        self.preset_placeholder_values.clear();
+        if !entry.answers.is_empty() && self.apply_answers(&entry.answers).is_ok() {
+            self.menu_index = 0;
+            self.current_step = self.visible_steps().len().saturating_sub(1);
+        }
+        self.phase = Phase::Confirm;
+        self.prepare_confirm_phase();
+    }
+
+    /// Pin or unpin the highlighted recent command, keeping it highlighted
+    fn toggle_pin_recent(&mut self) -> io::Result<()> {
+        let Some(command) = self.selected_recent().map(|e| e.command.clone()) else {
+            return Ok(());
+        };
+        if let Some(entry) = self
+            .history_entries
+            .iter_mut()
+            .find(|e| e.command == command)
+        {
+            entry.pinned = !entry.pinned;
+        }
+        history::sort(&mut self.history_entries);
+        if let Some(i) = self.recent().iter().position(|e| e.command == command) {
+            self.menu_index = 1 + i;
+        }
+        self.history
+            .save(&self.config_name(), &self.history_entries)
+    }
+
+    fn delete_recent(&mut self) -> io::Result<()> {
+        let Some(command) = self.selected_recent().map(|e| e.command.clone()) else {
+            return Ok(());
+        };
+        self.history_entries.retain(|e| e.command != command);
+        self.menu_index = self.menu_index.min(self.menu_item_count() - 1);
+        self.history
+            .save(&self.config_name(), &self.history_entries)
+    }
+
+    /// Text steps with a typed value, which a saved preset can turn into
+    /// placeholders
+    fn savable_text_steps(&self) -> Vec<&Step> {
+        self.visible_steps()
+            .into_iter()
+            .filter(|step| {
+                step.step_type == StepType::Text
+                    && matches!(self.answers.get(&step.id),
+                        Some(Answer::Text(text)) if !text.is_empty()
+                            && !self.config.placeholder_options.contains_key(text))
+            })
+            .collect()
+    }
+
+    fn open_save_preset(&mut self) {
+        self.phase = Phase::SavePreset;
+        self.text_buffer.clear();
+        self.save_index = 0;
+        self.save_placeholders.clear();
+        self.notice = None;
+    }
+
+    /// Switch the highlighted text step between keeping its value and
+    /// becoming a placeholder
+    fn toggle_save_placeholder(&mut self) {
+        let Some(id) = self
+            .savable_text_steps()
+            .get(self.save_index)
+            .map(|step| step.id.clone())
+        else {
+            return;
+        };
+        if !self.save_placeholders.remove(&id) {
+            self.save_placeholders.insert(id);
+        }
+    }
+
+    /// The flags of the command built so far, as a preset: text values the
+    /// user picked become `<step_id>` placeholders
+    fn preset_flags(&self) -> String {
+        let mut parts = Vec::new();
+        for step in &self.config.steps {
+            let Some(answer) = self.answers.get(&step.id) else {
+                continue;
+            };
+            if self.save_placeholders.contains(&step.id) {
+                parts.extend(step.flag.clone().map(Part::Shell));
+                parts.push(Part::Shell(format!("<{}>", step.id)));
+            } else {
+                parts.extend(self.step_flags(step, answer));
+            }
         }
+        output::display(&parts)
+    }
+
+    /// Write the answers as a preset labelled with the typed text to the
+    /// user's overlay for this config, and go back to Confirm
+    fn save_preset(&mut self) {
+        let name = self.config_name();
+        self.save_preset_with(|preset| config::save_user_preset(&name, preset));
+    }
+
+    /// Save the answers as a preset with `save`, which returns the file it
+    /// went to, and say how that went
+    fn save_preset_with(
+        &mut self,
+        save: impl FnOnce(&Preset) -> Result<std::path::PathBuf, config::ConfigError>,
+    ) {
+        let preset = Preset {
+            label: self.text_buffer.as_str().trim().to_string(),
+            flags: self.preset_flags(),
+        };
+        self.notice = Some(match save(&preset) {
+            Ok(path) => {
+                let notice = format!("Saved preset '{}' to {}", preset.label, path.display());
+                self.config.presets.push(preset);
+                notice
+            }
+            Err(e) => e.to_string(),
+        });
+        self.phase = Phase::Confirm;
     }
 
-    fn build_preset_command(&self) -> Option<String> {
+    fn preset_parts(&self) -> Option<Vec<Part>> {
         self.selected_preset().map(|preset| {
-            let mut flags = preset.flags.clone();
-            // Replace user-input placeholders with their values
+            let mut flags = Part::Shell(preset.flags.clone());
+            // Replace user-input placeholders with their values, escaped for
+            // the quotes the preset puts around them
             for (placeholder, value) in &self.preset_placeholder_values {
-                flags = flags.replace(placeholder, value);
+                flags = flags.substitute(placeholder, value);
             }
-            format!("{} {}", self.base_command.join(" "), flags)
+            let mut parts = self.base_parts();
+            parts.push(flags);
+            parts
         })
     }
 
     fn current_command(&self) -> String {
+        output::display(&self.current_parts())
+    }
+
+    fn current_parts(&self) -> Vec<Part> {
         if self.menu_index == 0 {
-            self.build_command()
+            self.build_parts()
+        } else if let Some(entry) = self.selected_recent() {
+            vec![recorded_part(entry)]
         } else {
-            self.build_preset_command().unwrap_or_default()
+            self.preset_parts().unwrap_or_default()
         }
     }
 
+    /// The highlighted menu item's command, if it isn't the wizard
+    fn menu_command(&self) -> Option<CommandLine> {
+        let parts = match self.selected_recent() {
+            Some(entry) => vec![recorded_part(entry)],
+            None => self.preset_parts()?,
+        };
+        Some(self.command_line(&parts))
+    }
+
     fn current_step(&self) -> Option<&Step> {
         self.visible_steps().get(self.current_step).copied()
     }
@@ -270,6 +771,8 @@ impl Wizard {
     }
 
     fn init_step(&mut self) {
+        self.filter = None;
+        self.viewport.reset();
         let Some(step) = self.current_step().cloned() else {
             return;
         };
@@ -288,17 +791,19 @@ impl Wizard {
                 self.multi_selected = vec![false; step.options.len()];
             }
         }
+        // An answer given before, e.g. when coming back to the step
+        self.restore_answer();
     }
 
-    fn save_answer(&mut self) {
-        let Some(step) = self.current_step().cloned() else {
-            return;
-        };
+    /// The current step's id and the answer its widget holds, as it would be
+    /// saved on Enter
+    fn current_answer(&self) -> Option<(String, Answer)> {
+        let step = self.current_step()?;
 
         let answer = match step.step_type {
             StepType::Choice => Answer::Choice(self.choice_index),
             StepType::Toggle => Answer::Toggle(self.toggle_value),
-            StepType::Text => Answer::Text(self.text_buffer.clone()),
+            StepType::Text => Answer::Text(self.text_buffer.as_str().to_string()),
             StepType::Multi => {
                 let indices: Vec<usize> = self
                     .multi_selected
@@ -311,10 +816,116 @@ impl Wizard {
             }
         };
 
-        self.answers.insert(step.id.clone(), answer);
+        Some((step.id.clone(), answer))
+    }
+
+    fn save_answer(&mut self) {
+        if let Some((id, answer)) = self.current_answer() {
+            self.answers.insert(id, answer);
+            self.prune_hidden_answers();
+        }
+    }
+
+    /// Forget answers to steps whose `when` no longer holds, so they don't
+    /// end up in the command. Repeats because pruning one answer can hide
+    /// steps that depended on it.
+    fn prune_hidden_answers(&mut self) {
+        loop {
+            let hidden: Vec<String> = self
+                .config
+                .steps
+                .iter()
+                .filter(|step| self.answers.contains_key(&step.id) && !self.should_show_step(step))
+                .map(|step| step.id.clone())
+                .collect();
+            if hidden.is_empty() {
+                break;
+            }
+            for id in hidden {
+                self.answers.remove(&id);
+            }
+        }
+    }
+
+    /// Load the current step's saved answer into its widget
+    fn restore_answer(&mut self) {
+        let Some(step) = self.current_step() else {
+            return;
+        };
+        let option_count = step.options.len();
+        let Some(answer) = self.answers.get(&step.id).cloned() else {
+            return;
+        };
+        match answer {
+            Answer::Choice(idx) => self.choice_index = idx,
+            Answer::Toggle(val) => self.toggle_value = val,
+            Answer::Text(text) => self.text_buffer.set(&text),
+            Answer::Multi(indices) => {
+                self.multi_selected = vec![false; option_count];
+                for idx in indices {
+                    if idx < self.multi_selected.len() {
+                        self.multi_selected[idx] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fill `answers` from `(step id, value)` pairs instead of the TUI.
+    /// Steps are walked in config order so `when` conditions see earlier
+    /// answers; visible steps without a value get the wizard's defaults.
+    pub fn apply_answers(&mut self, values: &[(String, AnswerValue)]) -> Result<(), AnswerError> {
+        if let Some((id, _)) = values
+            .iter()
+            .find(|(id, _)| !self.config.steps.iter().any(|s| &s.id == id))
+        {
+            return Err(AnswerError::UnknownStep(id.clone()));
+        }
+
+        self.answers.clear();
+        let steps = self.config.steps.clone();
+        for step in &steps {
+            // Later values win, matching how repeated flags usually behave
+            let value = values
+                .iter()
+                .rev()
+                .find(|(id, _)| id == &step.id)
+                .map(|(_, v)| v);
+
+            if !self.should_show_step(step) {
+                if value.is_some() {
+                    return Err(AnswerError::HiddenStep(
+                        step.id.clone(),
+                        describe_when(step),
+                    ));
+                }
+                continue;
+            }
+
+            let answer = match value {
+                Some(value) => parse_answer(step, value)?,
+                None => default_answer(step),
+            };
+
+            if let Answer::Choice(idx) = answer {
+                if let Some(opt) = step.options.get(idx) {
+                    if let Some(chain) = &opt.chain {
+                        return Err(AnswerError::Chain(
+                            step.id.clone(),
+                            opt.label.clone(),
+                            chain.config.clone(),
+                        ));
+                    }
+                }
+            }
+
+            self.answers.insert(step.id.clone(), answer);
+        }
+
+        Ok(())
     }
 
-    fn get_current_chain(&self) -> Option<String> {
+    fn get_current_chain(&self) -> Option<Chain> {
         let step = self.current_step()?;
         if step.step_type != StepType::Choice {
             return None;
@@ -322,7 +933,7 @@ impl Wizard {
         step.options.get(self.choice_index)?.chain.clone()
     }
 
-    fn next_step(&mut self) -> Option<String> {
+    fn next_step(&mut self) -> Option<Chain> {
         // Check for chain before saving
         let chain = self.get_current_chain();
         if chain.is_some() {
@@ -343,114 +954,150 @@ impl Wizard {
     }
 
     fn prepare_confirm_phase(&mut self) {
+        self.notice = None;
+        self.placeholder_queue.clear();
+        self.resolved_placeholders.clear();
         if self.has_placeholder_options() {
-            self.fetch_placeholder_values();
+            self.placeholder_queue = self.placeholder_queue_for(&self.current_command());
         }
+        self.activate_placeholder(0);
     }
 
     fn prev_step(&mut self) {
         if self.phase == Phase::Confirm {
             self.phase = Phase::Steps;
-            // Re-init the current step
+            // Back to the last step, with its answer
             self.init_step();
         } else if self.current_step > 0 {
             self.current_step -= 1;
             self.init_step();
-            // Restore previous answer
-            if let Some(step) = self.current_step() {
-                if let Some(answer) = self.answers.get(&step.id) {
-                    match answer {
-                        Answer::Choice(idx) => self.choice_index = *idx,
-                        Answer::Toggle(val) => self.toggle_value = *val,
-                        Answer::Text(text) => self.text_buffer = text.clone(),
-                        Answer::Multi(indices) => {
-                            if let Some(step) = self.current_step() {
-                                self.multi_selected = vec![false; step.options.len()];
-                                for &idx in indices {
-                                    if idx < self.multi_selected.len() {
-                                        self.multi_selected[idx] = true;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        }
+    }
+
+    fn open_review(&mut self) {
+        self.phase = Phase::Review;
+        self.reviewing = false;
+        self.review_index = 0;
+        self.filter = None;
+        self.viewport.reset();
+    }
+
+    /// Jump from the review screen to the highlighted step, with its answer
+    /// loaded for editing
+    fn edit_reviewed_step(&mut self) {
+        self.current_step = self.review_index;
+        self.phase = Phase::Steps;
+        self.reviewing = true;
+        self.init_step();
+    }
+
+    /// Save the step edited from the review screen. Steps the new answer
+    /// reveals are asked next; once all visible steps are answered, returns
+    /// to review. Returns a chain if the picked option links elsewhere.
+    fn finish_review_edit(&mut self) -> Option<Chain> {
+        if let Some(chain) = self.get_current_chain() {
+            return Some(chain);
+        }
+        self.save_answer();
+
+        let unanswered = self
+            .visible_steps()
+            .iter()
+            .position(|step| !self.answers.contains_key(&step.id));
+        match unanswered {
+            Some(pos) => {
+                self.current_step = pos;
+                self.init_step();
+            }
+            None => {
+                self.phase = Phase::Review;
+                self.reviewing = false;
+                self.review_index = self
+                    .review_index
+                    .min(self.visible_steps().len().saturating_sub(1));
+                self.filter = None;
             }
         }
+        None
+    }
+
+    /// Back to the review screen without saving the step being edited
+    fn discard_review_edit(&mut self) {
+        self.phase = Phase::Review;
+        self.reviewing = false;
+    }
+
+    /// Leave the review screen for Confirm, as if the last step was just answered
+    fn close_review(&mut self) {
+        self.current_step = self.visible_steps().len().saturating_sub(1);
+        self.phase = Phase::Confirm;
+        self.reviewing = false;
+        self.prepare_confirm_phase();
+    }
+
+    /// The flags an answered step adds to this wizard's command
+    fn step_flags(&self, step: &Step, answer: &Answer) -> Vec<Part> {
+        step_flags(step, answer, &self.config.placeholder_options)
+    }
+
+    /// A built command, with the argv to run it by unless it needs the shell
+    fn command_line(&self, parts: &[Part]) -> CommandLine {
+        CommandLine::new(parts, self.config.shell)
+    }
+
+    /// The command the wizard starts from, which the flags are added to
+    fn base_parts(&self) -> Vec<Part> {
+        self.base_command
+            .iter()
+            .map(|word| Part::word(word))
+            .collect()
     }
 
-    pub fn build_command(&self) -> String {
-        let mut parts: Vec<String> = self.base_command.clone();
+    fn build_parts(&self) -> Vec<Part> {
+        let mut parts = self.base_parts();
 
         for step in &self.config.steps {
-            let Some(answer) = self.answers.get(&step.id) else {
-                continue;
-            };
+            if let Some(answer) = self.answers.get(&step.id) {
+                parts.extend(self.step_flags(step, answer));
+            }
+        }
 
-            match (&step.step_type, answer) {
-                (StepType::Choice, Answer::Choice(idx)) => {
-                    if let Some(opt) = step.options.get(*idx) {
-                        if let Some(flag) = &opt.flag {
-                            parts.push(flag.clone());
-                        }
-                    }
-                }
-                (StepType::Toggle, Answer::Toggle(true)) => {
-                    if let Some(flag) = &step.flag {
-                        parts.push(flag.clone());
-                    }
-                }
-                (StepType::Text, Answer::Text(text)) => {
-                    if !text.is_empty() {
-                        if let Some(flag) = &step.flag {
-                            parts.push(format!("{} {}", flag, text));
-                        } else {
-                            parts.push(text.clone());
-                        }
-                    }
-                }
-                (StepType::Multi, Answer::Multi(indices)) => {
-                    for &idx in indices {
-                        if let Some(opt) = step.options.get(idx) {
-                            if let Some(flag) = &opt.flag {
-                                parts.push(flag.clone());
-                            }
-                        }
-                    }
-                }
-                _ => {}
+        parts
+    }
+
+    /// The parts of the command `build_command` would produce if the current
+    /// widget's answer were accepted, each marked with whether the current
+    /// step contributed it
+    fn preview_parts(&self) -> Vec<(String, bool)> {
+        let current = self.current_answer();
+        let mut parts: Vec<(String, bool)> = self
+            .base_parts()
+            .iter()
+            .map(|part| (part.shown().to_string(), false))
+            .collect();
+
+        for step in &self.config.steps {
+            let (answer, is_current) = match &current {
+                Some((id, answer)) if *id == step.id => (Some(answer), true),
+                _ => (self.answers.get(&step.id), false),
+            };
+            if let Some(answer) = answer {
+                parts.extend(
+                    self.step_flags(step, answer)
+                        .iter()
+                        .map(|part| (part.shown().to_string(), is_current)),
+                );
             }
         }
 
-        parts.join(" ")
+        parts
     }
 
     fn build_breadcrumb(&self) -> Vec<String> {
         self.config
             .steps
             .iter()
-            .filter_map(|step| {
-                let answer = self.answers.get(&step.id)?;
-                match (&step.step_type, answer) {
-                    (StepType::Choice, Answer::Choice(idx)) => {
-                        step.options.get(*idx).map(|opt| opt.label.clone())
-                    }
-                    (StepType::Toggle, Answer::Toggle(val)) => {
-                        Some(if *val { "Yes" } else { "No" }.to_string())
-                    }
-                    (StepType::Text, Answer::Text(text)) => {
-                        (!text.is_empty()).then(|| text.clone())
-                    }
-                    (StepType::Multi, Answer::Multi(indices)) => {
-                        let labels: Vec<&str> = indices
-                            .iter()
-                            .filter_map(|i| step.options.get(*i).map(|o| o.label.as_str()))
-                            .collect();
-                        (!labels.is_empty()).then(|| labels.join(", "))
-                    }
-                    _ => None,
-                }
-            })
+            .filter_map(|step| answer_label(step, self.answers.get(&step.id)?))
             .collect()
     }
 
@@ -462,93 +1109,756 @@ impl Wizard {
             .any(|p| cmd.contains(p))
     }
 
-    fn get_active_placeholder(&self) -> Option<(&str, &str)> {
-        let cmd = self.current_command();
-        self.config
-            .placeholder_options
-            .iter()
-            .find(|(placeholder, _)| cmd.contains(*placeholder))
-            .map(|(p, c)| (p.as_str(), c.as_str()))
+    /// Configured placeholders in the command, ordered by first appearance,
+    /// with any placeholder referenced by a fetch command queued before it.
+    fn placeholder_queue_for(&self, cmd: &str) -> Vec<String> {
+        let mut queue = Vec::new();
+        let mut visiting = Vec::new();
+        for placeholder in self.placeholders_in(cmd) {
+            self.queue_placeholder(&placeholder, &mut queue, &mut visiting);
+        }
+        queue
     }
 
-    fn fetch_placeholder_values(&mut self) {
-        self.placeholder_values.clear();
-        self.placeholder_index = 0;
-        self.active_placeholder = None;
+    fn placeholders_in(&self, text: &str) -> Vec<String> {
+        let mut found: Vec<(usize, &String)> = self
+            .config
+            .placeholder_options
+            .keys()
+            .filter_map(|p| text.find(p.as_str()).map(|idx| (idx, p)))
+            .collect();
+        found.sort();
+        found.into_iter().map(|(_, p)| p.clone()).collect()
+    }
 
-        let Some((placeholder, fetch_cmd)) = self.get_active_placeholder() else {
+    fn queue_placeholder(
+        &self,
+        placeholder: &str,
+        queue: &mut Vec<String>,
+        visiting: &mut Vec<String>,
+    ) {
+        if queue
+            .iter()
+            .chain(visiting.iter())
+            .any(|p| p == placeholder)
+        {
             return;
-        };
+        }
+        visiting.push(placeholder.to_string());
+        if let Some(option) = self.config.placeholder_options.get(placeholder) {
+            for dependency in self.placeholders_in(&option.command) {
+                self.queue_placeholder(&dependency, queue, visiting);
+            }
+        }
+        visiting.pop();
+        queue.push(placeholder.to_string());
+    }
+
+    fn placeholder_position(&self) -> Option<usize> {
+        let active = self.active_placeholder.as_ref()?;
+        self.placeholder_queue.iter().position(|p| p == active)
+    }
+
+    /// Make the placeholder at `pos` in the queue the one being picked
+    fn activate_placeholder(&mut self, pos: usize) {
+        self.active_placeholder = self.placeholder_queue.get(pos).cloned();
+        self.fetch_placeholder_values();
+    }
+
+    /// Record the highlighted value for the active placeholder and move on.
+    /// Returns true once every placeholder has been resolved.
+    fn next_placeholder(&mut self) -> bool {
+        let Some(pos) = self.placeholder_position() else {
+            return true;
+        };
+        if let Some((name, _)) = self.placeholder_values.get(self.placeholder_index) {
+            self.resolved_placeholders
+                .insert(self.placeholder_queue[pos].clone(), name.clone());
+        }
+        if pos + 1 < self.placeholder_queue.len() {
+            self.activate_placeholder(pos + 1);
+            false // More placeholders to pick
+        } else {
+            true // All done
+        }
+    }
 
-        // Copy values to avoid borrow issues
-        let placeholder = placeholder.to_string();
-        let fetch_cmd = fetch_cmd.to_string();
+    /// Return to the previous placeholder picker, highlighting the value that
+    /// was picked there. Returns false if already at the first one.
+    fn prev_placeholder(&mut self) -> bool {
+        let pos = match self.placeholder_position() {
+            Some(pos) if pos > 0 => pos,
+            _ => return false,
+        };
+        self.restore_pick = self
+            .resolved_placeholders
+            .remove(&self.placeholder_queue[pos - 1]);
+        self.activate_placeholder(pos - 1);
+        true
+    }
+
+    /// Placeholder picks made so far, in resolution order
+    fn placeholder_breadcrumb(&self) -> Vec<String> {
+        self.placeholder_queue
+            .iter()
+            .filter_map(|p| {
+                let value = self.resolved_placeholders.get(p)?;
+                Some(format!(
+                    "{}: {}",
+                    p.trim_matches(|c| c == '<' || c == '>'),
+                    value
+                ))
+            })
+            .collect()
+    }
 
-        self.active_placeholder = Some(placeholder);
+    /// Substitute already-picked placeholders into the command's parts
+    fn with_resolved_placeholders(&self, mut parts: Vec<Part>) -> Vec<Part> {
+        for (placeholder, value) in &self.resolved_placeholders {
+            parts = parts
+                .iter()
+                .map(|part| part.substitute(placeholder, value))
+                .collect();
+        }
+        parts
+    }
+
+    /// A placeholder's value, whether picked from fetched options or typed in
+    /// for a preset
+    fn placeholder_value(&self, placeholder: &str) -> Option<&String> {
+        self.resolved_placeholders
+            .get(placeholder)
+            .or_else(|| self.preset_placeholder_values.get(placeholder))
+    }
+
+    /// A step's answer as plain text: the entered text, the chosen label(s),
+    /// or true/false. Hidden or unanswered steps have no value.
+    fn answer_value(&self, step: &Step) -> Option<String> {
+        if !self.should_show_step(step) {
+            return None;
+        }
+        match self.answers.get(&step.id)? {
+            Answer::Choice(idx) => step.options.get(*idx).map(|o| o.label.clone()),
+            Answer::Toggle(val) => Some(val.to_string()),
+            Answer::Text(text) => Some(text.clone()),
+            Answer::Multi(indices) => Some(
+                indices
+                    .iter()
+                    .filter_map(|i| step.options.get(*i).map(|o| o.label.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+        }
+    }
 
-        let output = std::process::Command::new("sh")
-            .args(["-c", &fetch_cmd])
-            .output();
+    /// Value for a `{{name}}` reference, or None if `name` is neither a step
+    /// id nor a known placeholder
+    fn reference_value(&self, name: &str) -> Option<String> {
+        if name.starts_with('<') && name.ends_with('>') {
+            let known = self.config.placeholder_options.contains_key(name)
+                || self.preset_placeholder_values.contains_key(name);
+            return known.then(|| self.placeholder_value(name).cloned().unwrap_or_default());
+        }
+        let step = self.config.steps.iter().find(|s| s.id == name)?;
+        Some(self.answer_value(step).unwrap_or_default())
+    }
 
-        if let Ok(output) = output {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    let parts: Vec<&str> = line.split('\t').collect();
-                    if parts.len() == 2 {
-                        self.placeholder_values
-                            .push((parts[0].to_string(), parts[1].to_string()));
+    /// Fill `{{step_id}}`, `{{<placeholder>}}` and bare `<placeholder>`
+    /// references in a fetch command with shell-escaped values. Other `{{...}}`
+    /// text, such as docker's Go templates, is left untouched.
+    fn expand_fetch_command(&self, fetch_cmd: &str) -> String {
+        let mut expanded = String::new();
+        let mut rest = fetch_cmd;
+        let mut scanner = shell::Scanner::default();
+
+        while let Some(c) = rest.chars().next() {
+            if let Some(inner) = rest.strip_prefix("{{") {
+                if let Some(end) = inner.find("}}") {
+                    if let Some(value) = self.reference_value(inner[..end].trim()) {
+                        expanded.push_str(&shell::escape_in(&value, scanner.context()));
+                        rest = &inner[end + 2..];
+                        continue;
                     }
                 }
             }
+
+            let bare = self
+                .resolved_placeholders
+                .keys()
+                .chain(self.preset_placeholder_values.keys())
+                .find(|p| rest.starts_with(p.as_str()));
+            if let Some(placeholder) = bare {
+                let value = self.placeholder_value(placeholder).cloned();
+                let value = value.unwrap_or_default();
+                expanded.push_str(&shell::escape_in(&value, scanner.context()));
+                rest = &rest[placeholder.len()..];
+                continue;
+            }
+
+            expanded.push(c);
+            scanner.advance(c);
+            rest = &rest[c.len_utf8()..];
         }
+
+        expanded
     }
 
-    fn command_with_placeholder(&self, value: &str) -> String {
-        if let Some(placeholder) = &self.active_placeholder {
-            self.current_command().replace(placeholder, value)
-        } else {
-            self.current_command()
+    /// The active placeholder's fetch command with references filled in
+    fn active_fetch(&self) -> Option<(String, &PlaceholderOption)> {
+        let option = self
+            .active_placeholder
+            .as_ref()
+            .and_then(|p| self.config.placeholder_options.get(p))?;
+        // Earlier answers and picks can be referenced by later fetch commands
+        Some((self.expand_fetch_command(&option.command), option))
+    }
+
+    /// Start fetching options for the active placeholder in the background,
+    /// unless fresh values are cached
+    fn fetch_placeholder_values(&mut self) {
+        self.placeholder_values.clear();
+        self.placeholder_index = 0;
+        self.filter = None;
+        self.viewport.reset();
+        self.fetch = None;
+        self.fetch_error.clear();
+
+        let Some((fetch_cmd, option)) = self.active_fetch() else {
+            return;
+        };
+        let (ttl, persist) = cache_settings(option);
+        let timeout = option
+            .timeout
+            .map(Duration::from_secs)
+            .unwrap_or(fetch::DEFAULT_TIMEOUT);
+
+        if let Some(values) = self.cache.get(&fetch_cmd, ttl, persist) {
+            self.placeholder_values = values;
+            self.restore_pick();
+            return;
+        }
+
+        self.fetch = Some(PendingFetch::spawn(fetch_cmd, timeout));
+    }
+
+    /// Collect the result of a background fetch once it has finished
+    fn poll_fetch(&mut self) {
+        let Some(outcome) = self.fetch.as_ref().and_then(|f| f.try_finish()) else {
+            return;
+        };
+        let Some(fetch) = self.fetch.take() else {
+            return;
+        };
+
+        match outcome {
+            FetchOutcome::Values(values) => {
+                if let Some((_, option)) = self.active_fetch() {
+                    let (ttl, persist) = cache_settings(option);
+                    if !ttl.is_zero() {
+                        self.cache.insert(&fetch.command, values.clone(), persist);
+                    }
+                }
+                self.placeholder_values = values;
+                self.restore_pick();
+            }
+            outcome => self.fetch_error = outcome.error_lines(),
+        }
+    }
+
+    /// Stop a running fetch. Returns false if nothing was running.
+    fn cancel_fetch(&mut self) -> bool {
+        if self.fetch.take().is_none() {
+            return false;
+        }
+        self.fetch_error = FetchOutcome::Cancelled.error_lines();
+        true
+    }
+
+    /// Highlight the value picked earlier, after going back to a picker
+    fn restore_pick(&mut self) {
+        let Some(value) = self.restore_pick.take() else {
+            return;
+        };
+        if let Some(idx) = self
+            .placeholder_values
+            .iter()
+            .position(|(n, _)| *n == value)
+        {
+            self.placeholder_index = idx;
+        }
+    }
+
+    /// Drop cached values for the active placeholder and fetch them again
+    fn refresh_placeholder_values(&mut self) {
+        if let Some((fetch_cmd, _)) = self.active_fetch() {
+            self.cache.invalidate(&fetch_cmd);
+        }
+        self.fetch_placeholder_values();
+    }
+
+    fn command_with_placeholder(&self, value: &str) -> Vec<Part> {
+        let parts = self.with_resolved_placeholders(self.current_parts());
+        match &self.active_placeholder {
+            Some(placeholder) => parts
+                .iter()
+                .map(|part| part.substitute(placeholder, value))
+                .collect(),
+            None => parts,
+        }
+    }
+
+    /// The command as it would run now, using the highlighted value for the
+    /// placeholder being picked
+    fn confirm_command(&self) -> String {
+        output::display(&self.confirm_parts())
+    }
+
+    fn confirm_parts(&self) -> Vec<Part> {
+        match self.placeholder_values.get(self.placeholder_index) {
+            Some((name, _)) if self.active_placeholder.is_some() => {
+                self.command_with_placeholder(name)
+            }
+            _ => self.with_resolved_placeholders(self.current_parts()),
+        }
+    }
+
+    /// Labels of the list shown in the current phase, empty if there's none
+    fn list_labels(&self) -> Vec<String> {
+        match self.phase {
+            Phase::Menu => {
+                let mut labels = Vec::with_capacity(self.menu_item_count());
+                labels.push(MENU_WIZARD_LABEL.to_string());
+                labels.extend(self.recent().iter().map(|e| e.command.clone()));
+                labels.extend(self.config.presets.iter().map(|p| p.label.clone()));
+                labels
+            }
+            Phase::Steps => match self.current_step() {
+                Some(step) if matches!(step.step_type, StepType::Choice | StepType::Multi) => {
+                    step.options.iter().map(|o| o.label.clone()).collect()
+                }
+                _ => Vec::new(),
+            },
+            Phase::Confirm => self
+                .placeholder_values
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect(),
+            Phase::Review => self
+                .visible_steps()
+                .iter()
+                .map(|step| step.prompt.clone())
+                .collect(),
+            Phase::PresetInput | Phase::SavePreset => Vec::new(),
+        }
+    }
+
+    /// Items of the current list matching the filter, best match first, with
+    /// the char positions to highlight
+    fn filtered_items(&self) -> Vec<(usize, Vec<usize>)> {
+        let labels = self.list_labels();
+        match self.filter.as_deref() {
+            Some(query) if !query.is_empty() => fuzzy::rank(query, &labels),
+            _ => (0..labels.len()).map(|i| (i, Vec::new())).collect(),
+        }
+    }
+
+    /// Index of the highlighted item in the current list
+    fn list_cursor(&self) -> usize {
+        match self.phase {
+            Phase::Menu => self.menu_index,
+            Phase::Steps => self.choice_index,
+            Phase::Confirm => self.placeholder_index,
+            Phase::Review => self.review_index,
+            Phase::PresetInput | Phase::SavePreset => 0,
+        }
+    }
+
+    fn set_list_cursor(&mut self, idx: usize) {
+        match self.phase {
+            Phase::Menu => self.menu_index = idx,
+            Phase::Steps => self.choice_index = idx,
+            Phase::Confirm => self.placeholder_index = idx,
+            Phase::Review => self.review_index = idx,
+            Phase::PresetInput | Phase::SavePreset => {}
+        }
+    }
+
+    /// Move the highlight up or down, skipping items hidden by the filter
+    fn move_cursor(&mut self, delta: isize) {
+        let items = self.filtered_items();
+        let cursor = self.list_cursor();
+        let pos = match items.iter().position(|(i, _)| *i == cursor) {
+            Some(pos) => pos.saturating_add_signed(delta),
+            None => 0,
+        };
+        if let Some((idx, _)) = items.get(pos.min(items.len().saturating_sub(1))) {
+            self.set_list_cursor(*idx);
+        }
+    }
+
+    /// Whether the highlighted item is shown with the current filter
+    fn cursor_visible(&self) -> bool {
+        let cursor = self.list_cursor();
+        self.filtered_items().iter().any(|(i, _)| *i == cursor)
+    }
+
+    /// Row of the highlighted item among the filtered items
+    fn cursor_row(&self) -> usize {
+        let cursor = self.list_cursor();
+        self.filtered_items()
+            .iter()
+            .position(|(i, _)| *i == cursor)
+            .unwrap_or(0)
+    }
+
+    /// How far PageUp/PageDown move: one screenful of the list
+    fn page_size(&self) -> isize {
+        self.viewport.rows.get().max(1) as isize
+    }
+
+    fn set_filter(&mut self, query: String) {
+        self.filter = Some(query);
+        // Jump to the best match so Enter picks it
+        if let Some((idx, _)) = self.filtered_items().first() {
+            self.set_list_cursor(*idx);
+        }
+    }
+
+    /// Insert pasted text into whatever is being typed: the filter query or
+    /// a text input
+    fn paste(&mut self, text: &str) {
+        if let Some(query) = &self.filter {
+            let query = format!("{}{}", query, text.replace(['\r', '\n'], " "));
+            self.set_filter(query);
+            return;
+        }
+        let typing = match self.phase {
+            Phase::Steps => self.current_step().map(|s| &s.step_type) == Some(&StepType::Text),
+            Phase::PresetInput | Phase::SavePreset => true,
+            _ => false,
+        };
+        if typing {
+            self.text_buffer.insert_str(text);
+        }
+    }
+
+    /// Whether a printable key does something in the current phase, rather
+    /// than starting a filter. Matches the key handling in `run`.
+    fn is_shortcut(&self, c: char) -> bool {
+        if matches!(c, 'q' | 'j' | 'k') {
+            return true;
+        }
+        match self.phase {
+            Phase::Menu => matches!(c, 'p' | 'd') && self.selected_recent().is_some(),
+            Phase::Confirm => {
+                matches!(c, 'e' | 's') && self.menu_index == 0 && self.fetch.is_none()
+            }
+            _ => false,
+        }
+    }
+
+    /// Handle a key while type-to-filter applies. Typing a printable key that
+    /// isn't a shortcut here (or `/`) starts filtering the current list;
+    /// after that printable keys edit the query instead of acting as
+    /// shortcuts. Returns true if the key was consumed.
+    fn handle_filter_key(&mut self, key: &KeyEvent) -> bool {
+        let typed = match key.code {
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => Some(c),
+            _ => None,
+        };
+
+        let Some(query) = self.filter.clone() else {
+            let Some(c) = typed else {
+                return false;
+            };
+            if self.list_labels().is_empty() || c.is_whitespace() || self.is_shortcut(c) {
+                return false;
+            }
+            let query = if c == '/' {
+                String::new()
+            } else {
+                c.to_string()
+            };
+            self.set_filter(query);
+            return true;
+        };
+
+        match key.code {
+            KeyCode::Esc => {
+                self.filter = None;
+                true
+            }
+            KeyCode::Backspace if query.is_empty() => {
+                self.filter = None;
+                true
+            }
+            KeyCode::Backspace => {
+                let mut query = query;
+                query.pop();
+                self.set_filter(query);
+                true
+            }
+            // Nothing to pick when the filter hides everything
+            KeyCode::Enter if !self.cursor_visible() => true,
+            KeyCode::Enter
+                if self.phase == Phase::Steps
+                    && self.current_step().map(|s| &s.step_type) == Some(&StepType::Multi) =>
+            {
+                // Toggle the match and clear the query, ready to filter for the next one
+                if let Some(selected) = self.multi_selected.get_mut(self.choice_index) {
+                    *selected = !*selected;
+                }
+                self.filter = None;
+                true
+            }
+            _ => match typed {
+                Some(c) => {
+                    self.set_filter(format!("{}{}", query, c));
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+/// How an answer reads in the breadcrumb and review screen. None for empty
+/// text and multi answers.
+fn answer_label(step: &Step, answer: &Answer) -> Option<String> {
+    match (&step.step_type, answer) {
+        (StepType::Choice, Answer::Choice(idx)) => {
+            step.options.get(*idx).map(|opt| opt.label.clone())
+        }
+        (StepType::Toggle, Answer::Toggle(val)) => {
+            Some(if *val { "Yes" } else { "No" }.to_string())
+        }
+        (StepType::Text, Answer::Text(text)) => (!text.is_empty()).then(|| text.clone()),
+        (StepType::Multi, Answer::Multi(indices)) => {
+            let labels: Vec<&str> = indices
+                .iter()
+                .filter_map(|i| step.options.get(*i).map(|o| o.label.as_str()))
+                .collect();
+            (!labels.is_empty()).then(|| labels.join(", "))
+        }
+        _ => None,
+    }
+}
+
+/// The flags an answered step adds to the command. Typed text is quoted,
+/// unless it's one of `placeholders`, which is quoted when its value is
+/// picked.
+fn step_flags(
+    step: &Step,
+    answer: &Answer,
+    placeholders: &HashMap<String, PlaceholderOption>,
+) -> Vec<Part> {
+    let mut parts = Vec::new();
+    match (&step.step_type, answer) {
+        (StepType::Choice, Answer::Choice(idx)) => {
+            if let Some(flag) = step.options.get(*idx).and_then(|opt| opt.flag.as_ref()) {
+                parts.push(Part::Shell(flag.clone()));
+            }
+        }
+        (StepType::Toggle, Answer::Toggle(true)) => {
+            if let Some(flag) = &step.flag {
+                parts.push(Part::Shell(flag.clone()));
+            }
+        }
+        (StepType::Text, Answer::Text(text)) if !text.is_empty() => {
+            parts.extend(step.flag.clone().map(Part::Shell));
+            if placeholders.contains_key(text) {
+                parts.push(Part::Shell(text.clone()));
+            } else {
+                parts.push(Part::typed(text, step.quote));
+            }
+        }
+        (StepType::Multi, Answer::Multi(indices)) => {
+            for &idx in indices {
+                if let Some(flag) = step.options.get(idx).and_then(|opt| opt.flag.as_ref()) {
+                    parts.push(Part::Shell(flag.clone()));
+                }
+            }
+        }
+        _ => {}
+    }
+    parts
+}
+
+/// A recent command as it ran. Entries from before the words were recorded
+/// only have the command line.
+fn recorded_part(entry: &history::Entry) -> Part {
+    match &entry.argv {
+        Some(argv) => Part::Words {
+            shown: entry.command.clone(),
+            args: Some(argv.clone()),
+        },
+        None => Part::Shell(entry.command.clone()),
+    }
+}
+
+/// Cache TTL and persistence for a placeholder's fetch results
+fn cache_settings(option: &PlaceholderOption) -> (Duration, bool) {
+    let ttl = option
+        .cache_ttl
+        .map(Duration::from_secs)
+        .unwrap_or(cache::DEFAULT_TTL);
+    (ttl, option.cache_persist)
+}
+
+/// Find an option by label. Falls back to a case-insensitive match, then to
+/// a unique prefix so `Detached` selects "Detached (background)".
+fn find_option(step: &Step, label: &str) -> Result<usize, AnswerError> {
+    let lower = label.to_lowercase();
+    step.options
+        .iter()
+        .position(|o| o.label == label)
+        .or_else(|| {
+            step.options
+                .iter()
+                .position(|o| o.label.to_lowercase() == lower)
+        })
+        .or_else(|| {
+            let mut prefixed = step
+                .options
+                .iter()
+                .enumerate()
+                .filter(|(_, o)| o.label.to_lowercase().starts_with(&lower));
+            match (prefixed.next(), prefixed.next()) {
+                (Some((idx, _)), None) if !lower.is_empty() => Some(idx),
+                _ => None,
+            }
+        })
+        .ok_or_else(|| AnswerError::UnknownOption {
+            step: step.id.clone(),
+            label: label.to_string(),
+            options: step.options.iter().map(|o| o.label.clone()).collect(),
+        })
+}
+
+/// Parse a raw answer value for a step: option labels for choice steps,
+/// comma-separated labels for multi steps, true/false for toggles.
+fn parse_answer(step: &Step, value: &AnswerValue) -> Result<Answer, AnswerError> {
+    let value = match value {
+        AnswerValue::One(value) => value,
+        AnswerValue::List(labels) if step.step_type == StepType::Multi => {
+            return labels
+                .iter()
+                .map(|label| find_option(step, label))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Answer::Multi)
         }
+        AnswerValue::List(_) => return Err(AnswerError::NotMulti(step.id.clone())),
+    };
+    match step.step_type {
+        StepType::Choice => find_option(step, value).map(Answer::Choice),
+        StepType::Toggle => match value.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "y" | "on" | "1" => Ok(Answer::Toggle(true)),
+            "false" | "no" | "n" | "off" | "0" => Ok(Answer::Toggle(false)),
+            _ => Err(AnswerError::InvalidToggle(
+                step.id.clone(),
+                value.to_string(),
+            )),
+        },
+        StepType::Text => Ok(Answer::Text(value.to_string())),
+        StepType::Multi => value
+            .split(',')
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(|label| find_option(step, label))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Answer::Multi),
+    }
+}
+
+/// The answer a step starts with in the TUI, used when no value is given
+fn default_answer(step: &Step) -> Answer {
+    match step.step_type {
+        StepType::Choice => Answer::Choice(step.default.unwrap_or(0)),
+        StepType::Toggle => Answer::Toggle(false),
+        StepType::Text => Answer::Text(String::new()),
+        StepType::Multi => Answer::Multi(Vec::new()),
     }
 }
 
-pub fn run(config: Config, base_command: Vec<String>) -> io::Result<WizardResult> {
-    if config.steps.is_empty() {
+fn describe_when(step: &Step) -> String {
+    let mut conditions: Vec<String> = step
+        .when
+        .iter()
+        .flatten()
+        .map(|(id, value)| format!("{} = {}", id, value))
+        .collect();
+    conditions.sort();
+    conditions.join(", ")
+}
+
+/// Build the command from pre-supplied answers without opening the TUI
+pub fn run_headless(
+    config: Config,
+    base_command: Vec<String>,
+    values: &[(String, AnswerValue)],
+) -> Result<CommandLine, AnswerError> {
+    let mut wizard = Wizard::new(config, base_command);
+    wizard.apply_answers(values)?;
+    Ok(wizard.command_line(&wizard.build_parts()))
+}
+
+/// Show the wizard until a command is chosen or the user leaves it. The
+/// wizard keeps its state, so it can be run again after a chained one.
+pub fn run(wizard: &mut Wizard, cache: &mut FetchCache) -> io::Result<WizardResult> {
+    if wizard.config.steps.is_empty() {
         eprintln!("Config has no steps defined");
         return Ok(WizardResult::Quit);
     }
 
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
+    stdout().execute(EnableBracketedPaste)?;
 
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
-    let mut wizard = Wizard::new(config, base_command);
-    wizard.init_step();
+    std::mem::swap(&mut wizard.cache, cache);
 
     let result = loop {
-        terminal.draw(|f| ui(f, &wizard))?;
+        wizard.poll_fetch();
+        terminal.draw(|f| ui(f, wizard))?;
+
+        // Keep redrawing while a fetch runs so the spinner moves
+        if wizard.fetch.is_some() && !event::poll(SPINNER_INTERVAL)? {
+            continue;
+        }
+
+        let event = event::read()?;
+        if let Event::Paste(text) = &event {
+            wizard.paste(text);
+        }
+        if let Event::Key(key) = event {
+            if wizard.handle_filter_key(&key) {
+                continue;
+            }
+            let searchable = matches!(wizard.phase, Phase::Menu | Phase::Steps);
+            if key.code == KeyCode::Char('g')
+                && key.modifiers.contains(KeyModifiers::CONTROL)
+                && searchable
+            {
+                break Ok(WizardResult::Search);
+            }
 
-        if let Event::Key(key) = event::read()? {
             match wizard.phase {
                 Phase::Menu => match key.code {
                     KeyCode::Esc => break Ok(WizardResult::Back),
                     KeyCode::Char('q') => break Ok(WizardResult::Quit),
-                    KeyCode::Up | KeyCode::Char('k') => {
-                        if wizard.menu_index > 0 {
-                            wizard.menu_index -= 1;
-                        }
-                    }
-                    KeyCode::Down | KeyCode::Char('j') => {
-                        if wizard.menu_index + 1 < wizard.menu_item_count() {
-                            wizard.menu_index += 1;
-                        }
-                    }
+                    KeyCode::Up | KeyCode::Char('k') => wizard.move_cursor(-1),
+                    KeyCode::Down | KeyCode::Char('j') => wizard.move_cursor(1),
+                    KeyCode::PageUp => wizard.move_cursor(-wizard.page_size()),
+                    KeyCode::PageDown => wizard.move_cursor(wizard.page_size()),
+                    KeyCode::Home => wizard.move_cursor(isize::MIN),
+                    KeyCode::End => wizard.move_cursor(isize::MAX),
                     KeyCode::Enter => {
                         if wizard.menu_index == 0 {
                             // Start wizard
                             wizard.phase = Phase::Steps;
                             wizard.init_step();
+                        } else if wizard.selected_recent().is_some() {
+                            wizard.open_recent();
                         } else {
                             // Preset selected - check for user-input placeholders
                             wizard.prepare_preset_input();
@@ -563,12 +1873,20 @@ pub fn run(config: Config, base_command: Vec<String>) -> io::Result<WizardResult
                         }
                     }
                     KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        if let Some(cmd) = wizard.build_preset_command() {
+                        if let Some(cmd) = wizard.menu_command() {
                             break Ok(WizardResult::Command(cmd, OutputMode::Clipboard));
                         }
                     }
+                    // If saving fails, the change is saved with the next command run
+                    KeyCode::Char('p') if wizard.selected_recent().is_some() => {
+                        let _ = wizard.toggle_pin_recent();
+                    }
+                    KeyCode::Char('d') | KeyCode::Delete if wizard.selected_recent().is_some() => {
+                        let _ = wizard.delete_recent();
+                    }
                     KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        if let Some(cmd) = wizard.build_preset_command() {
+                        // One that can't run is left for Enter, where Confirm says why
+                        if let Some(cmd) = wizard.menu_command().filter(CommandLine::runnable) {
                             break Ok(WizardResult::Command(cmd, OutputMode::Execute));
                         }
                     }
@@ -577,10 +1895,22 @@ pub fn run(config: Config, base_command: Vec<String>) -> io::Result<WizardResult
                 Phase::Steps => {
                     let step_type = wizard.current_step().map(|s| s.step_type.clone());
 
+                    // Text input takes every key it can edit with, letters like q included
+                    if step_type == Some(StepType::Text) && wizard.text_buffer.handle_key(&key) {
+                        continue;
+                    }
+
                     match key.code {
+                        // Editing from the review screen: Esc discards the edit
+                        KeyCode::Esc if wizard.reviewing => wizard.discard_review_edit(),
+                        KeyCode::Enter if wizard.reviewing => {
+                            if let Some(chain) = wizard.finish_review_edit() {
+                                break Ok(WizardResult::Chain(chain));
+                            }
+                        }
                         KeyCode::Esc => {
                             if wizard.current_step == 0 {
-                                if wizard.config.presets.is_empty() {
+                                if !wizard.has_menu() {
                                     // No menu to go back to, go back to previous wizard
                                     break Ok(WizardResult::Back);
                                 } else {
@@ -597,24 +1927,12 @@ pub fn run(config: Config, base_command: Vec<String>) -> io::Result<WizardResult
                                 break Ok(WizardResult::Chain(chain));
                             }
                         }
-                        KeyCode::Up | KeyCode::Char('k') => match step_type {
-                            Some(StepType::Choice) | Some(StepType::Multi) => {
-                                if wizard.choice_index > 0 {
-                                    wizard.choice_index -= 1;
-                                }
-                            }
-                            _ => {}
-                        },
-                        KeyCode::Down | KeyCode::Char('j') => match step_type {
-                            Some(StepType::Choice) | Some(StepType::Multi) => {
-                                let len =
-                                    wizard.current_step().map(|s| s.options.len()).unwrap_or(0);
-                                if wizard.choice_index + 1 < len {
-                                    wizard.choice_index += 1;
-                                }
-                            }
-                            _ => {}
-                        },
+                        KeyCode::Up | KeyCode::Char('k') => wizard.move_cursor(-1),
+                        KeyCode::Down | KeyCode::Char('j') => wizard.move_cursor(1),
+                        KeyCode::PageUp => wizard.move_cursor(-wizard.page_size()),
+                        KeyCode::PageDown => wizard.move_cursor(wizard.page_size()),
+                        KeyCode::Home => wizard.move_cursor(isize::MIN),
+                        KeyCode::End => wizard.move_cursor(isize::MAX),
                         KeyCode::Left | KeyCode::Right | KeyCode::Char(' ') => match step_type {
                             Some(StepType::Toggle) => {
                                 wizard.toggle_value = !wizard.toggle_value;
@@ -627,42 +1945,24 @@ pub fn run(config: Config, base_command: Vec<String>) -> io::Result<WizardResult
                             }
                             _ => {}
                         },
-                        KeyCode::Char(c) => {
-                            if step_type == Some(StepType::Text) {
-                                wizard.text_buffer.push(c);
-                            }
-                        }
-                        KeyCode::Backspace => {
-                            if step_type == Some(StepType::Text) {
-                                wizard.text_buffer.pop();
-                            }
-                        }
                         _ => {}
                     }
                 }
+                Phase::PresetInput if wizard.text_buffer.handle_key(&key) => {}
                 Phase::PresetInput => match key.code {
-                    KeyCode::Esc => {
-                        if wizard.prev_preset_placeholder() {
-                            wizard.phase = Phase::Menu;
-                        }
-                    }
-                    KeyCode::Char('q') => break Ok(WizardResult::Quit),
-                    KeyCode::Enter => {
-                        if wizard.next_preset_placeholder() {
-                            // All placeholders filled, go to confirm
-                            wizard.phase = Phase::Confirm;
-                            wizard.prepare_confirm_phase();
-                        }
-                    }
-                    KeyCode::Char(c) => {
-                        wizard.text_buffer.push(c);
+                    KeyCode::Esc if wizard.prev_preset_placeholder() => {
+                        wizard.phase = Phase::Menu;
                     }
-                    KeyCode::Backspace => {
-                        wizard.text_buffer.pop();
+                    KeyCode::Enter if wizard.next_preset_placeholder() => {
+                        // All placeholders filled, go to confirm
+                        wizard.phase = Phase::Confirm;
+                        wizard.prepare_confirm_phase();
                     }
                     _ => {}
                 },
                 Phase::Confirm => match key.code {
+                    KeyCode::Esc if wizard.cancel_fetch() => {}
+                    KeyCode::Esc if wizard.prev_placeholder() => {}
                     KeyCode::Esc => {
                         if wizard.menu_index == 0 {
                             wizard.prev_step();
@@ -674,53 +1974,102 @@ pub fn run(config: Config, base_command: Vec<String>) -> io::Result<WizardResult
                         }
                     }
                     KeyCode::Char('q') => break Ok(WizardResult::Quit),
-                    KeyCode::Up | KeyCode::Char('k') => {
-                        if !wizard.placeholder_values.is_empty() && wizard.placeholder_index > 0 {
-                            wizard.placeholder_index -= 1;
-                        }
+                    KeyCode::Up | KeyCode::Char('k') => wizard.move_cursor(-1),
+                    KeyCode::Down | KeyCode::Char('j') => wizard.move_cursor(1),
+                    KeyCode::PageUp => wizard.move_cursor(-wizard.page_size()),
+                    KeyCode::PageDown => wizard.move_cursor(wizard.page_size()),
+                    KeyCode::Home => wizard.move_cursor(isize::MIN),
+                    KeyCode::End => wizard.move_cursor(isize::MAX),
+                    KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        wizard.refresh_placeholder_values();
                     }
-                    KeyCode::Down | KeyCode::Char('j') => {
-                        if wizard.placeholder_index + 1 < wizard.placeholder_values.len() {
-                            wizard.placeholder_index += 1;
-                        }
+                    KeyCode::Char('e') if wizard.menu_index == 0 && wizard.fetch.is_none() => {
+                        wizard.open_review();
                     }
-                    KeyCode::Enter => {
-                        let cmd = if wizard.has_placeholder_options()
-                            && !wizard.placeholder_values.is_empty()
-                        {
-                            let value = &wizard.placeholder_values[wizard.placeholder_index].0;
-                            wizard.command_with_placeholder(value)
-                        } else {
-                            wizard.current_command()
-                        };
-                        break Ok(WizardResult::Command(cmd, OutputMode::Execute));
+                    KeyCode::Char('s') if wizard.menu_index == 0 && wizard.fetch.is_none() => {
+                        wizard.open_save_preset();
+                    }
+                    KeyCode::Enter if wizard.fetch.is_none() && wizard.next_placeholder() => {
+                        let cmd = wizard.command_line(&wizard.confirm_parts());
+                        if cmd.runnable() {
+                            break Ok(WizardResult::Command(cmd, OutputMode::Execute));
+                        }
+                        wizard.notice = Some(NEEDS_SHELL_NOTICE.to_string());
                     }
                     KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                         break Ok(WizardResult::Command(
-                            wizard.current_command(),
+                            wizard.command_line(&wizard.confirm_parts()),
                             OutputMode::Clipboard,
                         ));
                     }
                     KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                         break Ok(WizardResult::Command(
-                            wizard.current_command(),
+                            wizard.command_line(&wizard.confirm_parts()),
                             OutputMode::Print,
                         ));
                     }
                     _ => {}
                 },
+                Phase::Review => match key.code {
+                    KeyCode::Esc => wizard.close_review(),
+                    KeyCode::Char('q') => break Ok(WizardResult::Quit),
+                    KeyCode::Up | KeyCode::Char('k') => wizard.move_cursor(-1),
+                    KeyCode::Down | KeyCode::Char('j') => wizard.move_cursor(1),
+                    KeyCode::PageUp => wizard.move_cursor(-wizard.page_size()),
+                    KeyCode::PageDown => wizard.move_cursor(wizard.page_size()),
+                    KeyCode::Home => wizard.move_cursor(isize::MIN),
+                    KeyCode::End => wizard.move_cursor(isize::MAX),
+                    KeyCode::Enter => wizard.edit_reviewed_step(),
+                    _ => {}
+                },
+                Phase::SavePreset if wizard.text_buffer.handle_key(&key) => {}
+                Phase::SavePreset => match key.code {
+                    KeyCode::Esc => wizard.phase = Phase::Confirm,
+                    KeyCode::Up => wizard.save_index = wizard.save_index.saturating_sub(1),
+                    KeyCode::Down => {
+                        let last = wizard.savable_text_steps().len().saturating_sub(1);
+                        wizard.save_index = (wizard.save_index + 1).min(last);
+                    }
+                    KeyCode::Tab => wizard.toggle_save_placeholder(),
+                    KeyCode::Enter if !wizard.text_buffer.as_str().trim().is_empty() => {
+                        wizard.save_preset();
+                    }
+                    _ => {}
+                },
             }
         }
     };
 
     disable_raw_mode()?;
+    stdout().execute(DisableBracketedPaste)?;
     stdout().execute(LeaveAlternateScreen)?;
+    std::mem::swap(&mut wizard.cache, cache);
+
+    if let Ok(WizardResult::Command(cmd, _)) = &result {
+        if let Err(e) = wizard.record_history(cmd) {
+            eprintln!("Warning: couldn't save to history: {}", e);
+        }
+    }
 
     result
 }
 
-fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
-    let x = area.x + (area.width.saturating_sub(width)) / 2;
+const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Most stderr lines shown for a failed fetch
+const MAX_ERROR_LINES: usize = 5;
+
+const BOX_WIDTH: u16 = 60;
+
+const MENU_WIZARD_LABEL: &str = "Interactive wizard...";
+
+/// Shown on Enter for a command with shell syntax, from a config that
+/// doesn't run commands through a shell
+const NEEDS_SHELL_NOTICE: &str = "This needs a shell to run. ^C copy or ^P print it instead.";
+
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
     let y = area.y + (area.height.saturating_sub(height)) / 2;
     Rect::new(x, y, width.min(area.width), height.min(area.height))
 }
@@ -730,38 +2079,65 @@ fn calculate_content_height(wizard: &Wizard) -> u16 {
     let warning_lines = if wizard.command_found { 0 } else { 2 };
 
     let content_lines = match wizard.phase {
+        Phase::Menu if is_ranked(wizard) => {
+            // Filter line + matches (or "No matches.") + warning
+            1 + wizard.filtered_items().len().max(1) + warning_lines
+        }
         Phase::Menu => {
-            // 1 empty line + 1 wizard option + separator lines + preset count + warning
-            let preset_lines = if wizard.config.presets.is_empty() {
-                0
-            } else {
-                2 + wizard.config.presets.len() // 2 for empty + "Quick presets:" label
-            };
-            1 + 1 + preset_lines + warning_lines
+            // 1 empty line + 1 wizard option + sections + warning, each section
+            // with 2 lines for empty + label
+            let section_lines = |len: usize| if len == 0 { 0 } else { 2 + len };
+            1 + 1
+                + section_lines(wizard.recent().len())
+                + section_lines(wizard.config.presets.len())
+                + warning_lines
         }
         Phase::Steps => {
             let step_lines = if let Some(step) = wizard.current_step() {
                 match step.step_type {
-                    StepType::Choice | StepType::Multi => step.options.len(),
+                    StepType::Choice | StepType::Multi => wizard.filtered_items().len().max(1),
                     StepType::Toggle => 1,
                     StepType::Text => 1,
                 }
             } else {
                 1
             };
-            step_lines + warning_lines
+            step_lines + 1 + preview_height(wizard) + warning_lines
         }
         Phase::PresetInput => {
             // Prompt + input + progress indicator
             4 + warning_lines
         }
         Phase::Confirm => {
-            // 5 base lines + placeholder options if any
-            5 + if wizard.has_placeholder_options() {
-                1 + wizard.placeholder_values.len().max(1)
-            } else {
+            // 5 base lines + picks so far + notice + placeholder options if any
+            let picks = if wizard.placeholder_breadcrumb().is_empty() {
                 0
-            } + warning_lines
+            } else {
+                1
+            };
+            let notice = usize::from(wizard.notice.is_some());
+            5 + picks
+                + notice
+                + if wizard.active_placeholder.is_some() {
+                    if wizard.fetch_error.is_empty() {
+                        1 + wizard.filtered_items().len().max(1)
+                    } else {
+                        wizard.fetch_error.len().min(MAX_ERROR_LINES + 1)
+                    }
+                } else {
+                    0
+                }
+                + warning_lines
+        }
+        Phase::Review => {
+            // Heading + steps (or "No matches.") + warning
+            1 + wizard.filtered_items().len().max(1) + warning_lines
+        }
+        Phase::SavePreset => {
+            // Label + input + preset command, then the text steps under a
+            // heading if there are any
+            let steps = wizard.savable_text_steps().len();
+            6 + if steps == 0 { 0 } else { 2 + steps } + warning_lines
         }
     };
 
@@ -769,6 +2145,118 @@ fn calculate_content_height(wizard: &Wizard) -> u16 {
     (content_lines as u16) + 8
 }
 
+/// Most rows the command preview wraps onto in the Steps phase
+const MAX_PREVIEW_LINES: usize = 3;
+
+/// Rows the wrapped command preview takes, not counting the blank line above it
+fn preview_height(wizard: &Wizard) -> usize {
+    // "$ " prefix + parts joined by spaces, in the box's inner width
+    let width = wizard
+        .preview_parts()
+        .iter()
+        .map(|(part, _)| part.chars().count() + 1)
+        .sum::<usize>()
+        + 1;
+    width
+        .div_ceil(BOX_WIDTH as usize - 2)
+        .clamp(1, MAX_PREVIEW_LINES)
+}
+
+/// Whether the menu shows ranked filter matches instead of its usual layout
+fn is_ranked(wizard: &Wizard) -> bool {
+    wizard.filter.as_deref().is_some_and(|q| !q.is_empty())
+}
+
+/// The filter query as typed, with a cursor
+fn filter_line(wizard: &Wizard) -> Option<Line<'static>> {
+    let query = wizard.filter.as_ref()?;
+    Some(Line::from(vec![
+        Span::styled(format!("/{}", query), Style::default().fg(Color::Cyan)),
+        Span::raw("█"),
+    ]))
+}
+
+/// A text input: the part of the value that fits in the box with the cursor
+/// drawn over it, or `hint` while it's empty
+fn input_line<'a>(editor: &LineEditor, hint: Span<'a>) -> Line<'a> {
+    if editor.is_empty() {
+        return Line::from(vec![hint, Span::raw("█")]);
+    }
+    let (visible, col) = editor.view(BOX_WIDTH as usize - 2);
+    let before: String = visible.chars().take(col).collect();
+    let mut rest = visible.chars().skip(col);
+    let cursor = match rest.next() {
+        Some(c) => Span::styled(c.to_string(), Style::default().reversed()),
+        None => Span::raw("█"),
+    };
+    Line::from(vec![
+        Span::raw(before),
+        cursor,
+        Span::raw(rest.collect::<String>()),
+    ])
+}
+
+fn no_matches_line() -> Line<'static> {
+    Line::from(Span::styled(
+        "No matches.",
+        Style::default().fg(Color::DarkGray),
+    ))
+}
+
+/// Marker and label of a list item, with the chars matched by the filter
+/// highlighted
+fn item_spans(marker: &str, label: &str, positions: &[usize], style: Style) -> Vec<Span<'static>> {
+    let matched = style.fg(Color::Yellow).underlined();
+    let mut spans = vec![Span::styled(marker.to_string(), style)];
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in label.chars().enumerate() {
+        let is_match = positions.contains(&i);
+        if is_match != run_matched && !run.is_empty() {
+            let run_style = if run_matched { matched } else { style };
+            spans.push(Span::styled(std::mem::take(&mut run), run_style));
+        }
+        run_matched = is_match;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, if run_matched { matched } else { style }));
+    }
+    spans
+}
+
+/// A menu entry: 0 is the interactive wizard, then recent commands, then presets
+fn menu_item_line(wizard: &Wizard, idx: usize, positions: &[usize]) -> Line<'static> {
+    let is_selected = wizard.menu_index == idx;
+    let style = if is_selected {
+        Style::default().fg(Color::Cyan).bold()
+    } else {
+        Style::default()
+    };
+    let marker = if is_selected { "● " } else { "○ " };
+
+    let recent = wizard.recent();
+    if let Some(entry) = idx.checked_sub(1).and_then(|i| recent.get(i)) {
+        let mut spans = item_spans(marker, &entry.command, positions, style);
+        if entry.pinned {
+            spans.push(Span::styled("  ★", Style::default().fg(Color::Yellow)));
+        }
+        return Line::from(spans);
+    }
+    let Some(preset) = idx
+        .checked_sub(1 + recent.len())
+        .and_then(|i| wizard.config.presets.get(i))
+    else {
+        return Line::from(item_spans(marker, MENU_WIZARD_LABEL, positions, style));
+    };
+    let mut spans = item_spans(marker, &preset.label, positions, style);
+    spans.push(Span::styled(
+        format!("  ({})", preset.flags),
+        Style::default().fg(Color::DarkGray),
+    ));
+    Line::from(spans)
+}
+
 fn command_not_found_warning(wizard: &Wizard) -> Option<Line<'static>> {
     if wizard.command_found {
         None
@@ -783,7 +2271,7 @@ fn command_not_found_warning(wizard: &Wizard) -> Option<Line<'static>> {
 
 fn ui(f: &mut Frame, wizard: &Wizard) {
     // Dynamic box sizing based on content
-    let box_width = 60u16;
+    let box_width = BOX_WIDTH;
     let min_height = 10u16;
     let max_height = f.area().height.saturating_sub(2); // Leave some margin
     let content_height = calculate_content_height(wizard);
@@ -800,63 +2288,62 @@ fn ui(f: &mut Frame, wizard: &Wizard) {
     match wizard.phase {
         Phase::Menu => {
             let mut lines: Vec<Line> = Vec::new();
-            lines.push(Line::from(""));
+            let mut cursor_line = 0;
 
-            // Interactive wizard option
-            let wizard_style = if wizard.menu_index == 0 {
-                Style::default().fg(Color::Cyan).bold()
-            } else {
-                Style::default()
-            };
-            let marker = if wizard.menu_index == 0 {
-                "● "
+            if is_ranked(wizard) {
+                // Best matches first, wizard and presets alike
+                let items = wizard.filtered_items();
+                if items.is_empty() {
+                    lines.push(no_matches_line());
+                }
+                for (idx, positions) in items {
+                    if idx == wizard.menu_index {
+                        cursor_line = lines.len();
+                    }
+                    lines.push(menu_item_line(wizard, idx, &positions));
+                }
             } else {
-                "○ "
-            };
-            lines.push(Line::from(Span::styled(
-                format!("{}Interactive wizard...", marker),
-                wizard_style,
-            )));
-
-            // Separator if there are presets
-            if !wizard.config.presets.is_empty() {
-                lines.push(Line::from(""));
-                lines.push(Line::from(Span::styled(
-                    "  Quick presets:",
-                    Style::default().fg(Color::DarkGray),
-                )));
-            }
-
-            // Preset options
-            for (i, preset) in wizard.config.presets.iter().enumerate() {
-                let idx = i + 1;
-                let is_selected = wizard.menu_index == idx;
-                let style = if is_selected {
-                    Style::default().fg(Color::Cyan).bold()
-                } else {
-                    Style::default()
-                };
-                let marker = if is_selected { "● " } else { "○ " };
-                lines.push(Line::from(vec![
-                    Span::styled(format!("{}{}", marker, preset.label), style),
-                    Span::styled(
-                        format!("  ({})", preset.flags),
-                        Style::default().fg(Color::DarkGray),
+                // Interactive wizard option
+                lines.push(menu_item_line(wizard, 0, &[]));
+
+                // Recent commands, then presets, each under a heading
+                let recent_count = wizard.recent().len();
+                let sections = [
+                    ("  Recent:", 1..1 + recent_count),
+                    (
+                        "  Quick presets:",
+                        1 + recent_count..wizard.menu_item_count(),
                     ),
-                ]));
-            }
-
-            // Show warning at the bottom if command not found
-            if let Some(warning) = command_not_found_warning(wizard) {
-                lines.push(Line::from(""));
-                lines.push(warning);
+                ];
+                for (heading, range) in sections {
+                    if range.is_empty() {
+                        continue;
+                    }
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(Span::styled(
+                        heading,
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                    for idx in range {
+                        if idx == wizard.menu_index {
+                            cursor_line = lines.len();
+                        }
+                        lines.push(menu_item_line(wizard, idx, &[]));
+                    }
+                }
             }
 
-            let block = Block::default().borders(Borders::ALL).title(title);
-            let paragraph = Paragraph::new(lines).block(block);
-            f.render_widget(paragraph, chunks[0]);
+            let header = vec![filter_line(wizard).unwrap_or_else(|| Line::from(""))];
+            render_list_box(f, chunks[0], wizard, &title, header, lines, cursor_line);
 
-            let help = Paragraph::new("↑↓ select  Enter confirm  q quit")
+            let help_text = if wizard.filter.is_some() {
+                "Type to filter  ↑↓ select  Enter pick  Esc clear"
+            } else if wizard.selected_recent().is_some() {
+                "↑↓ select  / filter  Enter open  p pin  d delete  q quit"
+            } else {
+                "↑↓ select  / filter  Enter confirm  ^G search all  q quit"
+            };
+            let help = Paragraph::new(help_text)
                 .style(Style::default().fg(Color::DarkGray))
                 .block(Block::default().borders(Borders::ALL));
             f.render_widget(help, chunks[1]);
@@ -866,7 +2353,21 @@ fn ui(f: &mut Frame, wizard: &Wizard) {
                 render_step(f, chunks[0], step, wizard, &title);
             }
 
-            let help = Paragraph::new("↑↓ select  Enter confirm  Esc back  q quit")
+            let step_type = wizard.current_step().map(|s| &s.step_type);
+            let help_text = match step_type {
+                Some(StepType::Multi) if wizard.filter.is_some() => {
+                    "Type to filter  ↑↓ select  Enter toggle  Esc clear"
+                }
+                _ if wizard.filter.is_some() => "Type to filter  ↑↓ select  Enter pick  Esc clear",
+                Some(StepType::Text) if wizard.reviewing => "Enter save  Esc discard",
+                _ if wizard.reviewing => "↑↓ select  Enter save  Esc discard  q quit",
+                Some(StepType::Choice) | Some(StepType::Multi) => {
+                    "↑↓ select  / filter  Enter confirm  Esc back  q quit"
+                }
+                Some(StepType::Text) => "←→ move  ^U clear  Enter confirm  Esc back",
+                _ => "↑↓ select  Enter confirm  Esc back  q quit",
+            };
+            let help = Paragraph::new(help_text)
                 .style(Style::default().fg(Color::DarkGray))
                 .block(Block::default().borders(Borders::ALL));
             f.render_widget(help, chunks[1]);
@@ -889,14 +2390,11 @@ fn ui(f: &mut Frame, wizard: &Wizard) {
                 wizard.preset_placeholders.len()
             );
 
-            let display = if wizard.text_buffer.is_empty() {
-                Span::styled(
-                    format!("Enter {}...", prompt_text.to_lowercase()),
-                    Style::default().fg(Color::DarkGray),
-                )
-            } else {
-                Span::styled(&wizard.text_buffer, Style::default())
-            };
+            let hint = Span::styled(
+                format!("Enter {}...", prompt_text.to_lowercase()),
+                Style::default().fg(Color::DarkGray),
+            );
+            let input = input_line(&wizard.text_buffer, hint);
 
             let mut content = vec![
                 Line::from(""),
@@ -908,7 +2406,7 @@ fn ui(f: &mut Frame, wizard: &Wizard) {
                     ),
                 ]),
                 Line::from(""),
-                Line::from(vec![display, Span::raw("█")]),
+                input,
             ];
 
             // Show warning at the bottom if command not found
@@ -921,14 +2419,14 @@ fn ui(f: &mut Frame, wizard: &Wizard) {
             let paragraph = Paragraph::new(content).block(block);
             f.render_widget(paragraph, chunks[0]);
 
-            let help = Paragraph::new("Enter confirm  Esc back  q quit")
+            let help = Paragraph::new("←→ move  ^U clear  Enter confirm  Esc back")
                 .style(Style::default().fg(Color::DarkGray))
                 .block(Block::default().borders(Borders::ALL));
             f.render_widget(help, chunks[1]);
         }
         Phase::Confirm => {
-            let cmd = wizard.current_command();
-            let show_placeholder_options = wizard.has_placeholder_options();
+            let cmd = wizard.confirm_command();
+            let show_placeholder_options = wizard.active_placeholder.is_some();
 
             let mut content = vec![
                 Line::from(""),
@@ -941,18 +2439,80 @@ fn ui(f: &mut Frame, wizard: &Wizard) {
                 Line::from(""),
             ];
 
-            if show_placeholder_options {
-                if wizard.placeholder_values.is_empty() {
+            let picks = wizard.placeholder_breadcrumb();
+            if !picks.is_empty() {
+                content.push(Line::from(Span::styled(
+                    picks.join(" › "),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            if let Some(notice) = &wizard.notice {
+                content.push(Line::from(Span::styled(
+                    notice.clone(),
+                    Style::default().fg(Color::Yellow),
+                )));
+            }
+
+            let mut lines: Vec<Line> = Vec::new();
+            if let Some(placeholder) = &wizard.active_placeholder {
+                let name = placeholder.trim_matches(|c| c == '<' || c == '>');
+                if let Some(fetch) = &wizard.fetch {
+                    let frame = (fetch.elapsed().as_millis() / SPINNER_INTERVAL.as_millis())
+                        as usize
+                        % SPINNER_FRAMES.len();
+                    content.push(Line::from(vec![
+                        Span::styled(
+                            format!("{} Fetching {}...", SPINNER_FRAMES[frame], name),
+                            Style::default().fg(Color::Cyan),
+                        ),
+                        Span::styled(
+                            format!("  ({:.1}s)", fetch.elapsed().as_secs_f32()),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                    ]));
+                } else if let Some((summary, stderr)) = wizard.fetch_error.split_first() {
+                    content.push(Line::from(Span::styled(
+                        summary.clone(),
+                        Style::default().fg(Color::Yellow),
+                    )));
+                    for line in stderr.iter().take(MAX_ERROR_LINES) {
+                        content.push(Line::from(Span::styled(
+                            line.clone(),
+                            Style::default().fg(Color::DarkGray),
+                        )));
+                    }
+                } else if wizard.placeholder_values.is_empty() {
                     content.push(Line::from(Span::styled(
                         "No options found.",
                         Style::default().fg(Color::Yellow),
                     )));
                 } else {
-                    content.push(Line::from(Span::styled(
-                        "Run on:",
+                    let mut heading = vec![Span::styled(
+                        format!("Select {}:", name),
                         Style::default().fg(Color::DarkGray),
-                    )));
-                    for (i, (name, id)) in wizard.placeholder_values.iter().enumerate() {
+                    )];
+                    if wizard.placeholder_queue.len() > 1 {
+                        heading.push(Span::styled(
+                            format!(
+                                "  ({}/{})",
+                                wizard.placeholder_position().unwrap_or(0) + 1,
+                                wizard.placeholder_queue.len()
+                            ),
+                            Style::default().fg(Color::DarkGray),
+                        ));
+                    }
+                    if let Some(filter) = filter_line(wizard) {
+                        heading.push(Span::raw("  "));
+                        heading.extend(filter.spans);
+                    }
+                    content.push(Line::from(heading));
+
+                    let items = wizard.filtered_items();
+                    if items.is_empty() {
+                        lines.push(no_matches_line());
+                    }
+                    for (i, positions) in items {
+                        let (name, id) = &wizard.placeholder_values[i];
                         let is_selected = i == wizard.placeholder_index;
                         let marker = if is_selected { "● " } else { "○ " };
                         let style = if is_selected {
@@ -960,16 +2520,134 @@ fn ui(f: &mut Frame, wizard: &Wizard) {
                         } else {
                             Style::default()
                         };
-                        let short_id = &id[..id.len().min(12)];
-                        content.push(Line::from(Span::styled(
-                            format!("{}{} ({})", marker, name, short_id),
-                            style,
-                        )));
+                        let mut spans = item_spans(marker, name, &positions, style);
+                        if !id.is_empty() {
+                            let short_id: String = id.chars().take(12).collect();
+                            spans.push(Span::styled(format!(" ({})", short_id), style));
+                        }
+                        lines.push(Line::from(spans));
                     }
                 }
             }
 
-            // Show warning at the bottom if command not found
+            let cursor_row = wizard.cursor_row();
+            render_list_box(f, chunks[0], wizard, &title, content, lines, cursor_row);
+
+            let more_to_pick = wizard
+                .placeholder_position()
+                .is_some_and(|pos| pos + 1 < wizard.placeholder_queue.len());
+            let help_text = if wizard.fetch.is_some() {
+                "Esc cancel  q quit"
+            } else if wizard.filter.is_some() {
+                "Type to filter  ↑↓ select  Enter pick  Esc clear"
+            } else if more_to_pick {
+                "/ filter  Enter next  ^R reload  Esc back  q quit"
+            } else if show_placeholder_options && !wizard.placeholder_values.is_empty() {
+                "/ filter  Enter run  ^R reload  ^C copy  Esc back  q quit"
+            } else if show_placeholder_options {
+                "^R reload  ^C copy  Esc back  q quit"
+            } else if wizard.menu_index == 0 {
+                "e edit  s save  ^C copy  Esc back  q quit"
+            } else {
+                "^C copy  Esc back  q quit"
+            };
+            let help = Paragraph::new(help_text)
+                .style(Style::default().fg(Color::DarkGray))
+                .block(Block::default().borders(Borders::ALL));
+            f.render_widget(help, chunks[1]);
+        }
+        Phase::Review => {
+            let steps = wizard.visible_steps();
+            let mut lines: Vec<Line> = Vec::new();
+            let items = wizard.filtered_items();
+            if items.is_empty() {
+                lines.push(no_matches_line());
+            }
+            for (i, positions) in items {
+                let step = steps[i];
+                let is_selected = i == wizard.review_index;
+                let marker = if is_selected { "● " } else { "○ " };
+                let style = if is_selected {
+                    Style::default().fg(Color::Cyan).bold()
+                } else {
+                    Style::default()
+                };
+                let mut spans = item_spans(marker, &step.prompt, &positions, style);
+                let answer = wizard
+                    .answers
+                    .get(&step.id)
+                    .and_then(|answer| answer_label(step, answer));
+                spans.push(match answer {
+                    Some(answer) => {
+                        Span::styled(format!("  {}", answer), Style::default().fg(Color::Green))
+                    }
+                    None => Span::styled("  —", Style::default().fg(Color::DarkGray)),
+                });
+                lines.push(Line::from(spans));
+            }
+
+            let header = vec![match filter_line(wizard) {
+                Some(filter) => filter,
+                None => Line::from(Span::styled(
+                    "Your answers:",
+                    Style::default().fg(Color::DarkGray),
+                )),
+            }];
+            let cursor_row = wizard.cursor_row();
+            render_list_box(f, chunks[0], wizard, &title, header, lines, cursor_row);
+
+            let help_text = if wizard.filter.is_some() {
+                "Type to filter  ↑↓ select  Enter pick  Esc clear"
+            } else {
+                "↑↓ select  / filter  Enter edit  Esc done  q quit"
+            };
+            let help = Paragraph::new(help_text)
+                .style(Style::default().fg(Color::DarkGray))
+                .block(Block::default().borders(Borders::ALL));
+            f.render_widget(help, chunks[1]);
+        }
+        Phase::SavePreset => {
+            let hint = Span::styled("Name this preset...", Style::default().fg(Color::DarkGray));
+            let command = format!(
+                "{} {}",
+                wizard.base_command.join(" "),
+                wizard.preset_flags()
+            );
+            let mut content = vec![
+                Line::from(""),
+                Line::from(Span::styled("Save as preset", Style::default().bold())),
+                Line::from(""),
+                input_line(&wizard.text_buffer, hint),
+                Line::from(""),
+                Line::from(Span::styled(command, Style::default().fg(Color::Green))),
+            ];
+
+            let steps = wizard.savable_text_steps();
+            if !steps.is_empty() {
+                content.push(Line::from(""));
+                content.push(Line::from(Span::styled(
+                    "Ask for each time instead of keeping:",
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            for (i, step) in steps.iter().enumerate() {
+                let is_selected = i == wizard.save_index;
+                let checkbox = if wizard.save_placeholders.contains(&step.id) {
+                    "[x] "
+                } else {
+                    "[ ] "
+                };
+                let style = if is_selected {
+                    Style::default().fg(Color::Cyan).bold()
+                } else {
+                    Style::default()
+                };
+                content.push(Line::from(Span::styled(
+                    format!("{}<{}>  {}", checkbox, step.id, step.prompt),
+                    style,
+                )));
+            }
+
             if let Some(warning) = command_not_found_warning(wizard) {
                 content.push(Line::from(""));
                 content.push(warning);
@@ -979,10 +2657,10 @@ fn ui(f: &mut Frame, wizard: &Wizard) {
             let paragraph = Paragraph::new(content).block(block);
             f.render_widget(paragraph, chunks[0]);
 
-            let help_text = if show_placeholder_options && !wizard.placeholder_values.is_empty() {
-                "↑↓ select  Enter run  ^C copy  Esc back  q quit"
+            let help_text = if steps.is_empty() {
+                "Enter save  Esc cancel"
             } else {
-                "^C copy  Esc back  q quit"
+                "↑↓ select  Tab toggle  Enter save  Esc cancel"
             };
             let help = Paragraph::new(help_text)
                 .style(Style::default().fg(Color::DarkGray))
@@ -992,6 +2670,44 @@ fn ui(f: &mut Frame, wizard: &Wizard) {
     }
 }
 
+/// Bordered box with fixed `header` lines, a scrolling list below them, and
+/// the command-not-found warning (if any) pinned to the bottom
+fn render_list_box(
+    f: &mut Frame,
+    area: Rect,
+    wizard: &Wizard,
+    title: &str,
+    header: Vec<Line>,
+    lines: Vec<Line>,
+    cursor_line: usize,
+) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title.to_string());
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let warning = command_not_found_warning(wizard);
+    let warning_height = if warning.is_some() { 2 } else { 0 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(header.len() as u16), // Header
+            Constraint::Min(0),                      // List
+            Constraint::Length(warning_height),      // Warning (if any)
+        ])
+        .split(inner);
+
+    f.render_widget(Paragraph::new(header), chunks[0]);
+    let lines = wizard
+        .viewport
+        .show(lines, cursor_line, chunks[1].height as usize);
+    f.render_widget(Paragraph::new(lines), chunks[1]);
+    if let Some(warning) = warning {
+        f.render_widget(Paragraph::new(vec![Line::from(""), warning]), chunks[2]);
+    }
+}
+
 fn render_step(f: &mut Frame, area: Rect, step: &Step, wizard: &Wizard, title: &str) {
     // Warning takes 2 lines (empty + warning text)
     let warning_height = if wizard.command_found { 0 } else { 2 };
@@ -1000,10 +2716,11 @@ fn render_step(f: &mut Frame, area: Rect, step: &Step, wizard: &Wizard, title: &
         .direction(Direction::Vertical)
         .margin(1)
         .constraints([
-            Constraint::Length(2),              // Prompt
-            Constraint::Min(1),                 // Options
-            Constraint::Length(1),              // Breadcrumb
-            Constraint::Length(warning_height), // Warning (if any)
+            Constraint::Length(2),                                 // Prompt
+            Constraint::Min(1),                                    // Options
+            Constraint::Length(1 + preview_height(wizard) as u16), // Command preview
+            Constraint::Length(1),                                 // Breadcrumb
+            Constraint::Length(warning_height),                    // Warning (if any)
         ])
         .split(area);
 
@@ -1013,11 +2730,13 @@ fn render_step(f: &mut Frame, area: Rect, step: &Step, wizard: &Wizard, title: &
         .title(title.to_string());
     f.render_widget(block, area);
 
-    // Prompt
-    let prompt = Paragraph::new(Line::from(Span::styled(
-        &step.prompt,
+    // Prompt, with the filter query below it while filtering
+    let mut prompt_lines = vec![Line::from(Span::styled(
+        step.prompt.clone(),
         Style::default().bold(),
-    )));
+    ))];
+    prompt_lines.extend(filter_line(wizard));
+    let prompt = Paragraph::new(prompt_lines);
     f.render_widget(prompt, inner_chunks[0]);
 
     // Breadcrumb
@@ -1028,21 +2747,42 @@ fn render_step(f: &mut Frame, area: Rect, step: &Step, wizard: &Wizard, title: &
             breadcrumb_text,
             Style::default().fg(Color::DarkGray),
         )));
-        f.render_widget(breadcrumb, inner_chunks[2]);
+        f.render_widget(breadcrumb, inner_chunks[3]);
     }
 
     // Warning at the bottom (if command not found)
     if let Some(warning) = command_not_found_warning(wizard) {
         let warning_content = vec![Line::from(""), warning];
         let warning_widget = Paragraph::new(warning_content);
-        f.render_widget(warning_widget, inner_chunks[3]);
+        f.render_widget(warning_widget, inner_chunks[4]);
     }
 
+    // Command preview, with the current step's flags highlighted
+    let mut preview = vec![Span::styled("$ ", Style::default().fg(Color::DarkGray))];
+    for (i, (part, is_current)) in wizard.preview_parts().into_iter().enumerate() {
+        if i > 0 {
+            preview.push(Span::raw(" "));
+        }
+        let style = if is_current {
+            Style::default().fg(Color::Yellow).bold()
+        } else {
+            Style::default().fg(Color::Green)
+        };
+        preview.push(Span::styled(part, style));
+    }
+    let preview =
+        Paragraph::new(vec![Line::from(""), Line::from(preview)]).wrap(Wrap { trim: false });
+    f.render_widget(preview, inner_chunks[2]);
+
     // Widget based on type
     match step.step_type {
         StepType::Choice => {
             let mut lines: Vec<Line> = Vec::new();
-            for (i, opt) in step.options.iter().enumerate() {
+            let items = wizard.filtered_items();
+            if items.is_empty() {
+                lines.push(no_matches_line());
+            }
+            for (i, positions) in items {
                 let marker = if i == wizard.choice_index {
                     "● "
                 } else {
@@ -1053,13 +2793,16 @@ fn render_step(f: &mut Frame, area: Rect, step: &Step, wizard: &Wizard, title: &
                 } else {
                     Style::default()
                 };
-                lines.push(Line::from(Span::styled(
-                    format!("{}{}", marker, opt.label),
+                lines.push(Line::from(item_spans(
+                    marker,
+                    &step.options[i].label,
+                    &positions,
                     style,
                 )));
             }
-            let list = Paragraph::new(lines);
-            f.render_widget(list, inner_chunks[1]);
+            let height = inner_chunks[1].height as usize;
+            let lines = wizard.viewport.show(lines, wizard.cursor_row(), height);
+            f.render_widget(Paragraph::new(lines), inner_chunks[1]);
         }
         StepType::Toggle => {
             let yes_style = if wizard.toggle_value {
@@ -1085,17 +2828,17 @@ fn render_step(f: &mut Frame, area: Rect, step: &Step, wizard: &Wizard, title: &
         }
         StepType::Text => {
             let placeholder = step.placeholder.as_deref().unwrap_or("Type here...");
-            let display = if wizard.text_buffer.is_empty() {
-                Span::styled(placeholder, Style::default().fg(Color::DarkGray))
-            } else {
-                Span::styled(&wizard.text_buffer, Style::default())
-            };
-            let input = Paragraph::new(Line::from(vec![display, Span::raw("█")]));
+            let hint = Span::styled(placeholder, Style::default().fg(Color::DarkGray));
+            let input = Paragraph::new(input_line(&wizard.text_buffer, hint));
             f.render_widget(input, inner_chunks[1]);
         }
         StepType::Multi => {
             let mut lines: Vec<Line> = Vec::new();
-            for (i, opt) in step.options.iter().enumerate() {
+            let items = wizard.filtered_items();
+            if items.is_empty() {
+                lines.push(no_matches_line());
+            }
+            for (i, positions) in items {
                 let selected = wizard.multi_selected.get(i).copied().unwrap_or(false);
                 let is_cursor = i == wizard.choice_index;
 
@@ -1107,13 +2850,16 @@ fn render_step(f: &mut Frame, area: Rect, step: &Step, wizard: &Wizard, title: &
                 } else {
                     Style::default()
                 };
-                lines.push(Line::from(Span::styled(
-                    format!("{}{}", checkbox, opt.label),
+                lines.push(Line::from(item_spans(
+                    checkbox,
+                    &step.options[i].label,
+                    &positions,
                     style,
                 )));
             }
-            let list = Paragraph::new(lines);
-            f.render_widget(list, inner_chunks[1]);
+            let height = inner_chunks[1].height as usize;
+            let lines = wizard.viewport.show(lines, wizard.cursor_row(), height);
+            f.render_widget(Paragraph::new(lines), inner_chunks[1]);
         }
     }
 }
@@ -1121,15 +2867,27 @@ fn render_step(f: &mut Frame, area: Rect, step: &Step, wizard: &Wizard, title: &
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{Config, Preset, Step, StepOption, StepType};
+    use crate::config::{Config, Preset, Quote, Step, StepOption, StepType};
+
+    impl Wizard {
+        fn build_command(&self) -> String {
+            output::display(&self.build_parts())
+        }
+
+        fn build_preset_command(&self) -> Option<String> {
+            self.preset_parts().map(|parts| output::display(&parts))
+        }
+    }
 
     fn make_config(steps: Vec<Step>) -> Config {
         Config {
-            _command: "test".to_string(),
-            _description: "".to_string(),
+            name: "test".to_string(),
+            command: "test".to_string(),
+            description: "".to_string(),
             steps,
             presets: vec![],
             placeholder_options: HashMap::new(),
+            shell: false,
         }
     }
 
@@ -1150,6 +2908,7 @@ mod tests {
             default: None,
             when: None,
             placeholder: None,
+            quote: Quote::Auto,
         }
     }
 
@@ -1163,6 +2922,7 @@ mod tests {
             default: None,
             when: None,
             placeholder: None,
+            quote: Quote::Auto,
         }
     }
 
@@ -1176,6 +2936,7 @@ mod tests {
             default: None,
             when: None,
             placeholder: None,
+            quote: Quote::Auto,
         }
     }
 
@@ -1196,6 +2957,7 @@ mod tests {
             default: None,
             when: None,
             placeholder: None,
+            quote: Quote::Auto,
         }
     }
 
@@ -1263,9 +3025,9 @@ mod tests {
         let config = make_config(vec![make_text_step("message", Some("-m"))]);
         let mut wizard = Wizard::new(config, vec!["git".to_string(), "commit".to_string()]);
 
-        wizard.text_buffer = "my commit".to_string();
+        wizard.text_buffer.set("my commit");
         wizard.save_answer();
-        assert_eq!(wizard.build_command(), "git commit -m my commit");
+        assert_eq!(wizard.build_command(), "git commit -m 'my commit'");
     }
 
     #[test]
@@ -1273,17 +3035,47 @@ mod tests {
         let config = make_config(vec![make_text_step("path", None)]);
         let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
 
-        wizard.text_buffer = "/tmp".to_string();
+        wizard.text_buffer.set("/tmp");
         wizard.save_answer();
         assert_eq!(wizard.build_command(), "ls /tmp");
     }
 
+    #[test]
+    fn test_build_command_text_hostile_input() {
+        let config = make_config(vec![make_text_step("message", Some("-m"))]);
+        let mut wizard = Wizard::new(config, vec!["git".to_string(), "commit".to_string()]);
+        wizard.text_buffer.set("don't $(rm -rf ~)");
+        wizard.save_answer();
+
+        assert_eq!(
+            wizard.build_command(),
+            r"git commit -m 'don'\''t $(rm -rf ~)'"
+        );
+    }
+
+    #[test]
+    fn test_build_command_text_quote_modes() {
+        let mut step = make_text_step("files", None);
+        step.quote = Quote::None;
+        let mut message = make_text_step("message", Some("-m"));
+        message.quote = Quote::Double;
+        let config = make_config(vec![step, message]);
+        let mut wizard = Wizard::new(config, vec!["tar".to_string()]);
+        wizard.text_buffer.set("src docs");
+        wizard.save_answer();
+        wizard.current_step = 1;
+        wizard.text_buffer.set("it's $HOME");
+        wizard.save_answer();
+
+        assert_eq!(wizard.build_command(), r#"tar src docs -m "it's \$HOME""#);
+    }
+
     #[test]
     fn test_build_command_text_empty() {
         let config = make_config(vec![make_text_step("path", None)]);
         let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
 
-        wizard.text_buffer = "".to_string();
+        wizard.text_buffer.set("");
         wizard.save_answer();
         assert_eq!(wizard.build_command(), "ls");
     }
@@ -1353,6 +3145,62 @@ mod tests {
         assert_eq!(wizard.build_command(), "ls -l -a");
     }
 
+    fn preview_text(wizard: &Wizard) -> String {
+        let parts: Vec<String> = wizard.preview_parts().into_iter().map(|(p, _)| p).collect();
+        parts.join(" ")
+    }
+
+    #[test]
+    fn test_preview_uses_highlighted_choice() {
+        let config = make_config(vec![
+            make_choice_step("format", vec![("Short", None), ("Long", Some("-l"))]),
+            make_toggle_step("all", "-a"),
+        ]);
+        let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
+        wizard.init_step();
+        assert_eq!(preview_text(&wizard), "ls");
+
+        wizard.choice_index = 1;
+        assert_eq!(
+            wizard.preview_parts(),
+            vec![("ls".to_string(), false), ("-l".to_string(), true)]
+        );
+        // Nothing is saved until Enter
+        assert!(wizard.answers.is_empty());
+
+        wizard.next_step();
+        wizard.toggle_value = true;
+        assert_eq!(
+            wizard.preview_parts(),
+            vec![
+                ("ls".to_string(), false),
+                ("-l".to_string(), false),
+                ("-a".to_string(), true)
+            ]
+        );
+        wizard.next_step();
+        assert_eq!(wizard.build_command(), "ls -l -a");
+    }
+
+    #[test]
+    fn test_preview_text_and_multi() {
+        let config = make_config(vec![
+            make_multi_step(
+                "features",
+                vec![("Logs", "--log"), ("Metrics", "--metrics")],
+            ),
+            make_text_step("name", Some("--name")),
+        ]);
+        let mut wizard = Wizard::new(config, vec!["app".to_string()]);
+        wizard.init_step();
+        wizard.multi_selected = vec![true, true];
+        assert_eq!(preview_text(&wizard), "app --log --metrics");
+
+        wizard.next_step();
+        wizard.text_buffer.set("web");
+        assert_eq!(preview_text(&wizard), "app --log --metrics --name web");
+    }
+
     // ========================
     // should_show_step tests
     // ========================
@@ -1468,7 +3316,7 @@ mod tests {
         let config = make_config(vec![make_text_step("name", None), conditional_step.clone()]);
         let mut wizard = Wizard::new(config, vec!["test".to_string()]);
 
-        wizard.text_buffer = "admin".to_string();
+        wizard.text_buffer.set("admin");
         wizard.save_answer();
 
         assert!(wizard.should_show_step(&conditional_step));
@@ -1484,7 +3332,7 @@ mod tests {
         let config = make_config(vec![make_text_step("name", None), conditional_step.clone()]);
         let mut wizard = Wizard::new(config, vec!["test".to_string()]);
 
-        wizard.text_buffer = "user".to_string();
+        wizard.text_buffer.set("user");
         wizard.save_answer();
 
         assert!(!wizard.should_show_step(&conditional_step));
@@ -1622,7 +3470,7 @@ mod tests {
         let config = make_config(vec![make_text_step("path", None)]);
         let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
 
-        wizard.text_buffer = "/tmp".to_string();
+        wizard.text_buffer.set("/tmp");
         wizard.save_answer();
 
         let crumbs = wizard.build_breadcrumb();
@@ -1634,7 +3482,7 @@ mod tests {
         let config = make_config(vec![make_text_step("path", None)]);
         let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
 
-        wizard.text_buffer = "".to_string();
+        wizard.text_buffer.set("");
         wizard.save_answer();
 
         let crumbs = wizard.build_breadcrumb();
@@ -1720,7 +3568,7 @@ mod tests {
     fn test_init_step_text() {
         let config = make_config(vec![make_text_step("opt", None)]);
         let mut wizard = Wizard::new(config, vec!["test".to_string()]);
-        wizard.text_buffer = "something".to_string();
+        wizard.text_buffer.set("something");
         wizard.init_step();
 
         assert!(wizard.text_buffer.is_empty());
@@ -1798,21 +3646,39 @@ mod tests {
         let mut config = make_config(vec![make_text_step("container", None)]);
         config.placeholder_options.insert(
             "<container>".to_string(),
-            "docker ps --format '{{.Names}}'".to_string(),
+            "docker ps --format '{{.Names}}'".into(),
         );
 
         let mut wizard = Wizard::new(config, vec!["docker".to_string(), "logs".to_string()]);
-        wizard.text_buffer = "<container>".to_string();
+        wizard.text_buffer.set("<container>");
         wizard.save_answer();
 
         assert!(wizard.has_placeholder_options());
     }
 
+    #[test]
+    fn test_typed_angle_brackets_are_quoted() {
+        let mut config = make_config(vec![make_text_step("message", Some("-m"))]);
+        config
+            .placeholder_options
+            .insert("<container>".to_string(), "docker ps".into());
+
+        // Only a configured placeholder is left for the user to pick
+        let mut wizard = Wizard::new(config, vec!["git".to_string(), "commit".to_string()]);
+        wizard.text_buffer.set("<wip>");
+        wizard.save_answer();
+        assert_eq!(wizard.build_command(), "git commit -m '<wip>'");
+
+        wizard.text_buffer.set("<container>");
+        wizard.save_answer();
+        assert_eq!(wizard.build_command(), "git commit -m <container>");
+    }
+
     #[test]
     fn test_has_placeholder_options_none() {
         let config = make_config(vec![make_text_step("path", None)]);
         let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
-        wizard.text_buffer = "/tmp".to_string();
+        wizard.text_buffer.set("/tmp");
         wizard.save_answer();
 
         assert!(!wizard.has_placeholder_options());
@@ -1823,92 +3689,985 @@ mod tests {
         let mut config = make_config(vec![make_text_step("container", None)]);
         config
             .placeholder_options
-            .insert("<container>".to_string(), "docker ps".to_string());
+            .insert("<container>".to_string(), "docker ps".into());
 
         let mut wizard = Wizard::new(config, vec!["docker".to_string(), "logs".to_string()]);
-        wizard.text_buffer = "<container>".to_string();
+        wizard.text_buffer.set("<container>");
         wizard.save_answer();
         wizard.active_placeholder = Some("<container>".to_string());
 
         let cmd = wizard.command_with_placeholder("my_container");
-        assert_eq!(cmd, "docker logs my_container");
+        assert_eq!(output::display(&cmd), "docker logs my_container");
+    }
+
+    #[test]
+    fn test_command_with_placeholder_no_active() {
+        let config = make_config(vec![make_text_step("path", None)]);
+        let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
+        wizard.text_buffer.set("/tmp");
+        wizard.save_answer();
+
+        let cmd = wizard.command_with_placeholder("ignored");
+        assert_eq!(output::display(&cmd), "ls /tmp");
+    }
+
+    /// Block until a background placeholder fetch has finished
+    fn wait_for_fetch(wizard: &mut Wizard) {
+        while wizard.fetch.is_some() {
+            std::thread::sleep(Duration::from_millis(5));
+            wizard.poll_fetch();
+        }
+    }
+
+    fn make_preset_wizard(flags: &str, options: &[(&str, &str)]) -> Wizard {
+        let mut config = make_config(vec![]);
+        config.presets = vec![Preset {
+            label: "Preset".to_string(),
+            flags: flags.to_string(),
+        }];
+        for (placeholder, fetch_cmd) in options {
+            config
+                .placeholder_options
+                .insert(placeholder.to_string(), (*fetch_cmd).into());
+        }
+        let mut wizard = Wizard::new(config, vec!["kubectl".to_string(), "logs".to_string()]);
+        wizard.menu_index = 1;
+        wizard
+    }
+
+    #[test]
+    fn test_placeholder_queue_follows_command_order() {
+        let wizard = make_preset_wizard(
+            "<pod> -c <container>",
+            &[("<container>", "true"), ("<pod>", "true")],
+        );
+        assert_eq!(
+            wizard.placeholder_queue_for(&wizard.current_command()),
+            vec!["<pod>", "<container>"]
+        );
+    }
+
+    #[test]
+    fn test_placeholder_queue_puts_dependencies_first() {
+        let wizard = make_preset_wizard(
+            "-f <pod> -n <namespace>",
+            &[
+                ("<pod>", "kubectl get pods -n <namespace>"),
+                ("<namespace>", "kubectl get ns"),
+            ],
+        );
+        assert_eq!(
+            wizard.placeholder_queue_for(&wizard.current_command()),
+            vec!["<namespace>", "<pod>"]
+        );
+    }
+
+    #[test]
+    fn test_placeholder_queue_ignores_cycles() {
+        let wizard = make_preset_wizard("<a> <b>", &[("<a>", "echo <b>"), ("<b>", "echo <a>")]);
+        assert_eq!(
+            wizard.placeholder_queue_for(&wizard.current_command()),
+            vec!["<b>", "<a>"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_every_placeholder() {
+        let mut wizard = make_preset_wizard(
+            "-n <namespace> -f <pod>",
+            &[
+                ("<namespace>", "printf 'default\\nprod\\n'"),
+                (
+                    "<pod>",
+                    "printf '<namespace>-web\\t1\\n<namespace>-db\\t2\\n'",
+                ),
+            ],
+        );
+        wizard.phase = Phase::Confirm;
+        wizard.prepare_confirm_phase();
+        wait_for_fetch(&mut wizard);
+
+        assert_eq!(wizard.active_placeholder.as_deref(), Some("<namespace>"));
+        assert_eq!(
+            wizard.placeholder_values,
+            vec![
+                ("default".to_string(), "".to_string()),
+                ("prod".to_string(), "".to_string())
+            ]
+        );
+
+        // Pick "prod", which feeds into the <pod> fetch command
+        wizard.placeholder_index = 1;
+        assert!(!wizard.next_placeholder());
+        wait_for_fetch(&mut wizard);
+        assert_eq!(wizard.active_placeholder.as_deref(), Some("<pod>"));
+        assert_eq!(wizard.placeholder_values[0].0, "prod-web");
+        assert_eq!(wizard.placeholder_breadcrumb(), vec!["namespace: prod"]);
+
+        wizard.placeholder_index = 1;
+        assert_eq!(wizard.confirm_command(), "kubectl logs -n prod -f prod-db");
+        assert!(wizard.next_placeholder());
+        assert_eq!(wizard.confirm_command(), "kubectl logs -n prod -f prod-db");
+    }
+
+    #[test]
+    fn test_prev_placeholder_restores_pick() {
+        let mut wizard = make_preset_wizard(
+            "-n <namespace> -f <pod>",
+            &[
+                ("<namespace>", "printf 'default\\nprod\\n'"),
+                ("<pod>", "printf 'web\\n'"),
+            ],
+        );
+        wizard.phase = Phase::Confirm;
+        wizard.prepare_confirm_phase();
+        wait_for_fetch(&mut wizard);
+
+        // Already at the first placeholder
+        assert!(!wizard.prev_placeholder());
+
+        wizard.placeholder_index = 1;
+        wizard.next_placeholder();
+        assert!(wizard.prev_placeholder());
+        wait_for_fetch(&mut wizard);
+        assert_eq!(wizard.active_placeholder.as_deref(), Some("<namespace>"));
+        assert_eq!(wizard.placeholder_index, 1);
+        assert!(wizard.resolved_placeholders.is_empty());
+    }
+
+    #[test]
+    fn test_expand_fetch_command_step_references() {
+        let config = make_config(vec![
+            make_text_step("namespace", Some("-n")),
+            make_choice_step("output", vec![("Wide", Some("-o wide")), ("Name", None)]),
+            make_toggle_step("all", "-A"),
+        ]);
+        let mut wizard = Wizard::new(config, vec!["kubectl".to_string()]);
+        wizard.text_buffer.set("kube-system");
+        wizard.save_answer();
+        wizard.current_step = 1;
+        wizard.choice_index = 0;
+        wizard.save_answer();
+        wizard.current_step = 2;
+        wizard.toggle_value = true;
+        wizard.save_answer();
+
+        assert_eq!(
+            wizard.expand_fetch_command("kubectl get pods -n {{namespace}} # {{ output }} {{all}}"),
+            "kubectl get pods -n kube-system # Wide true"
+        );
+    }
+
+    #[test]
+    fn test_expand_fetch_command_escapes_values() {
+        let config = make_config(vec![make_text_step("namespace", Some("-n"))]);
+        let mut wizard = Wizard::new(config, vec!["kubectl".to_string()]);
+        wizard.text_buffer.set("x; rm -rf ~ #'");
+        wizard.save_answer();
+
+        assert_eq!(
+            wizard.expand_fetch_command("kubectl get pods -n {{namespace}}"),
+            r"kubectl get pods -n 'x; rm -rf ~ #'\'''"
+        );
+    }
+
+    #[test]
+    fn test_expand_fetch_command_missing_answer_is_empty_word() {
+        let config = make_config(vec![make_text_step("namespace", Some("-n"))]);
+        let wizard = Wizard::new(config, vec!["kubectl".to_string()]);
+        assert_eq!(
+            wizard.expand_fetch_command("ns={{namespace}}; kubectl get pods"),
+            "ns=''; kubectl get pods"
+        );
+    }
+
+    #[test]
+    fn test_expand_fetch_command_placeholder_references() {
+        let mut wizard = make_preset_wizard(
+            "-n <namespace> -f <pod>",
+            &[("<namespace>", "true"), ("<pod>", "true")],
+        );
+        wizard
+            .resolved_placeholders
+            .insert("<namespace>".to_string(), "my ns".to_string());
+
+        assert_eq!(
+            wizard.expand_fetch_command("kubectl get pods -n {{<namespace>}}"),
+            "kubectl get pods -n 'my ns'"
+        );
+        assert_eq!(
+            wizard.expand_fetch_command("kubectl get pods -n <namespace>"),
+            "kubectl get pods -n 'my ns'"
+        );
+    }
+
+    #[test]
+    fn test_expand_fetch_command_leaves_go_templates() {
+        let wizard = make_preset_wizard("<container>", &[("<container>", "true")]);
+        let fetch_cmd = "docker ps --format '{{.Names}}\\t{{.ID}}' {{unknown}}";
+        assert_eq!(wizard.expand_fetch_command(fetch_cmd), fetch_cmd);
+    }
+
+    #[test]
+    fn test_dependent_fetch_uses_step_answer() {
+        let mut config = make_config(vec![make_text_step("namespace", Some("-n"))]);
+        config.placeholder_options.insert(
+            "<pod>".to_string(),
+            "printf '%s-web\\n' {{namespace}}".into(),
+        );
+        let mut wizard = Wizard::new(config, vec!["kubectl".to_string(), "logs".to_string()]);
+        wizard.text_buffer.set("prod; echo pwned");
+        wizard.save_answer();
+        wizard.active_placeholder = Some("<pod>".to_string());
+        wizard.fetch_placeholder_values();
+        wait_for_fetch(&mut wizard);
+
+        assert_eq!(
+            wizard.placeholder_values,
+            vec![("prod; echo pwned-web".to_string(), "".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_fetch_uses_cache_until_refreshed() {
+        let mut wizard = make_preset_wizard("-f <pod>", &[("<pod>", "printf 'live\\n'")]);
+        wizard.cache.insert(
+            "printf 'live\\n'",
+            vec![("cached".to_string(), "".to_string())],
+            false,
+        );
+        wizard.active_placeholder = Some("<pod>".to_string());
+
+        wizard.fetch_placeholder_values();
+        wait_for_fetch(&mut wizard);
+        assert_eq!(wizard.placeholder_values[0].0, "cached");
+
+        wizard.refresh_placeholder_values();
+        wait_for_fetch(&mut wizard);
+        assert_eq!(wizard.placeholder_values[0].0, "live");
+    }
+
+    #[test]
+    fn test_fetch_zero_ttl_skips_cache() {
+        let mut wizard = make_preset_wizard("-f <pod>", &[]);
+        wizard.config.placeholder_options.insert(
+            "<pod>".to_string(),
+            PlaceholderOption {
+                command: "printf 'live\\n'".to_string(),
+                cache_ttl: Some(0),
+                cache_persist: false,
+                timeout: None,
+            },
+        );
+        wizard.active_placeholder = Some("<pod>".to_string());
+        wizard.fetch_placeholder_values();
+        wait_for_fetch(&mut wizard);
+        assert_eq!(
+            wizard
+                .cache
+                .get("printf 'live\\n'", cache::DEFAULT_TTL, false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_failed_fetch_shows_error() {
+        let mut wizard =
+            make_preset_wizard("-f <pod>", &[("<pod>", "echo 'no cluster' >&2; exit 2")]);
+        wizard.phase = Phase::Confirm;
+        wizard.prepare_confirm_phase();
+        wait_for_fetch(&mut wizard);
+
+        assert!(wizard.placeholder_values.is_empty());
+        assert_eq!(
+            wizard.fetch_error,
+            vec!["Fetch failed (exit code 2)", "no cluster"]
+        );
+    }
+
+    #[test]
+    fn test_cancel_fetch() {
+        let mut wizard = make_preset_wizard("-f <pod>", &[("<pod>", "sleep 5")]);
+        wizard.phase = Phase::Confirm;
+        wizard.prepare_confirm_phase();
+
+        assert!(wizard.fetch.is_some());
+        assert!(wizard.cancel_fetch());
+        assert!(wizard.fetch.is_none());
+        assert_eq!(wizard.fetch_error, vec!["Fetch cancelled"]);
+        assert!(!wizard.cancel_fetch());
+    }
+
+    #[test]
+    fn test_preset_input_skips_configured_placeholders() {
+        let mut wizard =
+            make_preset_wizard("--since=<duration> <pod>", &[("<pod>", "printf 'web\\n'")]);
+        wizard.prepare_preset_input();
+        assert_eq!(wizard.preset_placeholders, vec!["<duration>"]);
+    }
+
+    // ====================
+    // Preset tests
+    // ====================
+
+    #[test]
+    fn test_wizard_with_presets_starts_in_menu() {
+        let mut config = make_config(vec![make_toggle_step("a", "-a")]);
+        config.presets = vec![Preset {
+            label: "Quick".to_string(),
+            flags: "-la".to_string(),
+        }];
+
+        let wizard = Wizard::new(config, vec!["ls".to_string()]);
+        assert_eq!(wizard.phase, Phase::Menu);
+    }
+
+    #[test]
+    fn test_wizard_without_presets_starts_in_steps() {
+        let config = make_config(vec![make_toggle_step("a", "-a")]);
+        let wizard = Wizard::new(config, vec!["ls".to_string()]);
+        assert_eq!(wizard.phase, Phase::Steps);
+    }
+
+    #[test]
+    fn test_build_preset_command() {
+        let mut config = make_config(vec![]);
+        config.presets = vec![
+            Preset {
+                label: "Quick".to_string(),
+                flags: "-la".to_string(),
+            },
+            Preset {
+                label: "Verbose".to_string(),
+                flags: "-lah".to_string(),
+            },
+        ];
+
+        let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
+
+        // Select first preset
+        wizard.menu_index = 1;
+        assert_eq!(wizard.build_preset_command(), Some("ls -la".to_string()));
+
+        // Select second preset
+        wizard.menu_index = 2;
+        assert_eq!(wizard.build_preset_command(), Some("ls -lah".to_string()));
+    }
+
+    #[test]
+    fn test_menu_item_count() {
+        let mut config = make_config(vec![]);
+        config.presets = vec![
+            Preset {
+                label: "A".to_string(),
+                flags: "-a".to_string(),
+            },
+            Preset {
+                label: "B".to_string(),
+                flags: "-b".to_string(),
+            },
+        ];
+
+        let wizard = Wizard::new(config, vec!["ls".to_string()]);
+        assert_eq!(wizard.menu_item_count(), 3); // wizard + 2 presets
+    }
+
+    // ====================
+    // Recent commands tests
+    // ====================
+
+    fn recent_entry(command: &str, answers: &[(&str, &str)]) -> history::Entry {
+        history::Entry {
+            command: command.to_string(),
+            argv: None,
+            answers: answers
+                .iter()
+                .map(|(id, v)| (id.to_string(), AnswerValue::from(*v)))
+                .collect(),
+            pinned: false,
+            count: 1,
+            last_used: 0,
+        }
+    }
+
+    fn make_recent_wizard() -> Wizard {
+        let mut config = make_config(vec![
+            make_choice_step("format", vec![("List", Some("-l")), ("Grid", None)]),
+            make_toggle_step("hidden", "-a"),
+        ]);
+        config.presets = vec![Preset {
+            label: "All".to_string(),
+            flags: "-la".to_string(),
+        }];
+        let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
+        wizard.history_entries = vec![
+            recent_entry("ls -l -a", &[("format", "List"), ("hidden", "true")]),
+            recent_entry("ls -la", &[]),
+        ];
+        wizard
+    }
+
+    #[test]
+    fn test_recent_entries_come_before_presets() {
+        let mut wizard = make_recent_wizard();
+        assert_eq!(wizard.menu_item_count(), 4); // wizard + 2 recent + 1 preset
+
+        wizard.menu_index = 2;
+        assert_eq!(wizard.selected_recent().unwrap().command, "ls -la");
+        assert!(wizard.selected_preset().is_none());
+
+        wizard.menu_index = 3;
+        assert!(wizard.selected_recent().is_none());
+        assert_eq!(wizard.selected_preset().unwrap().label, "All");
+        assert_eq!(wizard.list_labels()[1], "ls -l -a");
+    }
+
+    #[test]
+    fn test_open_recent_restores_wizard_answers() {
+        let mut wizard = make_recent_wizard();
+        wizard.menu_index = 1;
+        wizard.open_recent();
+
+        assert_eq!(wizard.phase, Phase::Confirm);
+        assert_eq!(wizard.menu_index, 0);
+        assert_eq!(wizard.confirm_command(), "ls -l -a");
+
+        // Stepping back shows the saved answer
+        wizard.prev_step();
+        assert_eq!(wizard.current_step().unwrap().id, "hidden");
+        assert!(wizard.toggle_value);
+    }
+
+    #[test]
+    fn test_open_recent_preset_command() {
+        let mut wizard = make_recent_wizard();
+        wizard.menu_index = 2;
+        wizard.open_recent();
+
+        assert_eq!(wizard.phase, Phase::Confirm);
+        assert_eq!(wizard.confirm_command(), "ls -la");
+    }
+
+    #[test]
+    fn test_open_recent_with_stale_answers_replays_command() {
+        let mut wizard = make_recent_wizard();
+        wizard.history_entries[0].answers = vec![("gone".to_string(), AnswerValue::from("x"))];
+        wizard.menu_index = 1;
+        wizard.open_recent();
+
+        assert_eq!(wizard.menu_index, 1);
+        assert_eq!(wizard.confirm_command(), "ls -l -a");
+    }
+
+    #[test]
+    fn test_pin_and_delete_recent() {
+        let mut wizard = make_recent_wizard();
+        wizard.menu_index = 2;
+        wizard.toggle_pin_recent().unwrap();
+        // Pinned moves to the top, still highlighted
+        assert_eq!(wizard.menu_index, 1);
+        assert_eq!(wizard.selected_recent().unwrap().command, "ls -la");
+        assert!(wizard.selected_recent().unwrap().pinned);
+
+        wizard.delete_recent().unwrap();
+        assert_eq!(wizard.recent().len(), 1);
+        assert_eq!(wizard.selected_recent().unwrap().command, "ls -l -a");
+    }
+
+    #[test]
+    fn test_record_history_saves_wizard_answers() {
+        let mut wizard = make_recent_wizard();
+        wizard.history_entries.clear();
+        wizard
+            .answers
+            .insert("format".to_string(), Answer::Choice(1));
+        wizard
+            .answers
+            .insert("hidden".to_string(), Answer::Toggle(true));
+        wizard
+            .record_history(&wizard.command_line(&wizard.build_parts()))
+            .unwrap();
+
+        let entry = &wizard.history_entries[0];
+        assert_eq!(entry.command, "ls -a");
+        assert_eq!(
+            entry.answers,
+            vec![
+                ("format".to_string(), AnswerValue::from("Grid")),
+                ("hidden".to_string(), AnswerValue::from("true"))
+            ]
+        );
+
+        // Preset commands have no answers to restore
+        wizard.menu_index = 1 + wizard.recent().len();
+        wizard
+            .record_history(&wizard.menu_command().unwrap())
+            .unwrap();
+        assert!(wizard.history_entries[0].answers.is_empty());
+    }
+
+    #[test]
+    fn test_recent_multi_answer_round_trips_with_commas() {
+        let config = make_config(vec![make_multi_step(
+            "cols",
+            vec![("Name, size", "-ns"), ("Date", "-t")],
+        )]);
+        let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
+        wizard
+            .answers
+            .insert("cols".to_string(), Answer::Multi(vec![0, 1]));
+        wizard
+            .record_history(&wizard.command_line(&wizard.build_parts()))
+            .unwrap();
+
+        let json = serde_json::to_string(&wizard.history_entries).unwrap();
+        wizard.history_entries = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            wizard.history_entries[0].answers,
+            vec![(
+                "cols".to_string(),
+                AnswerValue::List(vec!["Name, size".to_string(), "Date".to_string()])
+            )]
+        );
+
+        wizard.answers.clear();
+        wizard.menu_index = 1;
+        wizard.open_recent();
+        assert!(matches!(&wizard.answers["cols"], Answer::Multi(indices) if indices == &[0, 1]));
+        assert_eq!(wizard.confirm_command(), "ls -ns -t");
+    }
+
+    #[test]
+    fn test_history_opens_menu_without_presets() {
+        let dir = std::env::temp_dir().join(format!("i-wizard-history-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let history = History::new(Some(dir.clone()));
+
+        let config = make_config(vec![make_toggle_step("hidden", "-a")]);
+        let mut wizard = Wizard::new(config.clone(), vec!["ls".to_string()]);
+        wizard.load_history(history.clone());
+        assert_eq!(wizard.phase, Phase::Steps);
+
+        history
+            .save("test", &[recent_entry("ls -a", &[("hidden", "true")])])
+            .unwrap();
+        let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
+        wizard.load_history(history);
+        assert_eq!(wizard.phase, Phase::Menu);
+        assert_eq!(wizard.recent().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // ====================
+    // Save preset tests
+    // ====================
+
+    fn make_save_wizard() -> Wizard {
+        let config = make_config(vec![
+            make_toggle_step("detach", "-d"),
+            make_text_step("name", Some("--name")),
+            make_text_step("image", None),
+            make_text_step("cmd", None),
+        ]);
+        let mut wizard = Wizard::new(config, vec!["docker".to_string(), "run".to_string()]);
+        wizard
+            .apply_answers(&answers(&[
+                ("detach", "yes"),
+                ("name", "my web"),
+                ("image", "nginx"),
+                ("cmd", ""),
+            ]))
+            .unwrap();
+        wizard.phase = Phase::Confirm;
+        wizard
+    }
+
+    #[test]
+    fn test_preset_flags_keep_answers() {
+        let mut wizard = make_save_wizard();
+        wizard.open_save_preset();
+        assert_eq!(wizard.phase, Phase::SavePreset);
+        assert_eq!(wizard.preset_flags(), "-d --name 'my web' nginx");
+    }
+
+    #[test]
+    fn test_preset_flags_with_placeholders() {
+        let mut wizard = make_save_wizard();
+        wizard.open_save_preset();
+        // Empty text answers aren't offered
+        let ids: Vec<&str> = wizard
+            .savable_text_steps()
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect();
+        assert_eq!(ids, ["name", "image"]);
+
+        wizard.toggle_save_placeholder();
+        assert_eq!(wizard.preset_flags(), "-d --name <name> nginx");
+        assert_eq!(
+            Wizard::extract_preset_placeholders(&wizard.preset_flags()),
+            ["<name>"]
+        );
+        wizard.toggle_save_placeholder();
+        assert_eq!(wizard.preset_flags(), "-d --name 'my web' nginx");
+    }
+
+    // ====================
+    // Filter tests
+    // ====================
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn type_keys(wizard: &mut Wizard, text: &str) {
+        for c in text.chars() {
+            assert!(wizard.handle_filter_key(&key(KeyCode::Char(c))));
+        }
+    }
+
+    fn make_filter_wizard() -> Wizard {
+        let config = make_config(vec![make_choice_step(
+            "mode",
+            vec![
+                ("Foreground (no TTY)", None),
+                ("Interactive (-it)", Some("-it")),
+                ("Detached (background)", Some("-d")),
+            ],
+        )]);
+        let mut wizard = Wizard::new(config, vec!["docker".to_string()]);
+        wizard.init_step();
+        wizard
+    }
+
+    #[test]
+    fn test_filter_jumps_to_top_match() {
+        let mut wizard = make_filter_wizard();
+        type_keys(&mut wizard, "/back");
+        assert_eq!(wizard.filter.as_deref(), Some("back"));
+        assert_eq!(wizard.filtered_items().len(), 1);
+        assert_eq!(wizard.choice_index, 2);
+
+        wizard.next_step();
+        assert!(matches!(
+            wizard.answers.get("mode"),
+            Some(Answer::Choice(2))
+        ));
+    }
+
+    #[test]
+    fn test_filter_starts_on_unbound_keys() {
+        let mut wizard = make_filter_wizard();
+        // Shortcuts keep working
+        assert!(!wizard.handle_filter_key(&key(KeyCode::Char('j'))));
+        assert!(!wizard.handle_filter_key(&key(KeyCode::Char(' '))));
+        assert!(wizard.filter.is_none());
+
+        type_keys(&mut wizard, "det");
+        assert_eq!(wizard.filter.as_deref(), Some("det"));
+        assert_eq!(wizard.choice_index, 2);
+
+        // Once filtering, shortcut keys are part of the query
+        wizard.filter = None;
+        type_keys(&mut wizard, "/jkq");
+        assert_eq!(wizard.filter.as_deref(), Some("jkq"));
+    }
+
+    #[test]
+    fn test_filter_navigation_skips_hidden_items() {
+        let mut wizard = make_filter_wizard();
+        type_keys(&mut wizard, "/nd");
+        let visible: Vec<usize> = wizard.filtered_items().iter().map(|(i, _)| *i).collect();
+        assert!(!visible.contains(&1));
+        assert_eq!(wizard.choice_index, visible[0]);
+
+        wizard.move_cursor(1);
+        assert_eq!(wizard.choice_index, visible[1]);
+        wizard.move_cursor(1);
+        assert_eq!(wizard.choice_index, visible[1]);
+    }
+
+    #[test]
+    fn test_filter_backspace_and_esc() {
+        let mut wizard = make_filter_wizard();
+        type_keys(&mut wizard, "/x");
+        assert!(wizard.filtered_items().is_empty());
+        // Enter does nothing while nothing matches
+        assert!(wizard.handle_filter_key(&key(KeyCode::Enter)));
+
+        assert!(wizard.handle_filter_key(&key(KeyCode::Backspace)));
+        assert_eq!(wizard.filtered_items().len(), 3);
+        assert!(wizard.handle_filter_key(&key(KeyCode::Backspace)));
+        assert!(wizard.filter.is_none());
+
+        type_keys(&mut wizard, "/det");
+        assert!(wizard.handle_filter_key(&key(KeyCode::Esc)));
+        assert!(wizard.filter.is_none());
+        assert_eq!(wizard.filtered_items().len(), 3);
+    }
+
+    #[test]
+    fn test_filter_multi_enter_toggles_match() {
+        let config = make_config(vec![make_multi_step(
+            "features",
+            vec![("Logging", "--log"), ("Metrics", "--metrics")],
+        )]);
+        let mut wizard = Wizard::new(config, vec!["app".to_string()]);
+        wizard.init_step();
+
+        type_keys(&mut wizard, "/met");
+        assert!(wizard.handle_filter_key(&key(KeyCode::Enter)));
+        assert_eq!(wizard.multi_selected, vec![false, true]);
+        assert!(wizard.filter.is_none());
+        assert_eq!(wizard.phase, Phase::Steps);
+    }
+
+    #[test]
+    fn test_filter_menu_ranks_presets() {
+        let mut config = make_config(vec![make_text_step("name", None)]);
+        config.presets = vec![
+            Preset {
+                label: "List all".to_string(),
+                flags: "-la".to_string(),
+            },
+            Preset {
+                label: "Human-readable".to_string(),
+                flags: "-lah".to_string(),
+            },
+        ];
+        let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
+
+        type_keys(&mut wizard, "human");
+        assert_eq!(wizard.menu_index, 2);
+        assert_eq!(wizard.selected_preset().unwrap().flags, "-lah");
+    }
+
+    #[test]
+    fn test_filter_placeholder_values() {
+        let mut wizard = make_preset_wizard(
+            "-f <pod>",
+            &[("<pod>", "printf 'web-1\\nworker-1\\nweb-2\\n'")],
+        );
+        wizard.phase = Phase::Confirm;
+        wizard.prepare_confirm_phase();
+        wait_for_fetch(&mut wizard);
+
+        type_keys(&mut wizard, "/wk");
+        assert_eq!(wizard.placeholder_index, 1);
+        assert_eq!(wizard.confirm_command(), "kubectl logs -f worker-1");
+    }
+
+    #[test]
+    fn test_filter_reset_on_next_step() {
+        let config = make_config(vec![
+            make_choice_step("a", vec![("One", None), ("Two", None)]),
+            make_choice_step("b", vec![("Three", None), ("Four", None)]),
+        ]);
+        let mut wizard = Wizard::new(config, vec!["cmd".to_string()]);
+        wizard.init_step();
+
+        type_keys(&mut wizard, "/two");
+        wizard.next_step();
+        assert!(wizard.filter.is_none());
+        assert_eq!(wizard.choice_index, 0);
+    }
+
+    #[test]
+    fn test_paste_goes_to_text_input_or_filter() {
+        let config = make_config(vec![make_text_step("msg", Some("-m"))]);
+        let mut wizard = Wizard::new(config, vec!["git".to_string()]);
+        wizard.init_step();
+        wizard.paste("fix: it's\nbroken");
+        assert_eq!(wizard.text_buffer.as_str(), "fix: it's broken");
+
+        let mut wizard = make_filter_wizard();
+        wizard.paste("ignored");
+        assert!(wizard.filter.is_none());
+        type_keys(&mut wizard, "/");
+        wizard.paste("back");
+        assert_eq!(wizard.choice_index, 2);
+    }
+
+    // ====================
+    // Scrolling tests
+    // ====================
+
+    fn numbered_lines(n: usize) -> Vec<Line<'static>> {
+        (0..n).map(|i| Line::from(i.to_string())).collect()
+    }
+
+    fn line_text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_viewport_fits_without_scrolling() {
+        let viewport = Viewport::default();
+        let lines = viewport.show(numbered_lines(3), 2, 5);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(viewport.offset.get(), 0);
+    }
+
+    #[test]
+    fn test_viewport_keeps_cursor_visible() {
+        let viewport = Viewport::default();
+        // 5 rows: indicator, 3 items, indicator
+        let lines = viewport.show(numbered_lines(10), 0, 5);
+        assert_eq!(lines.len(), 5);
+        assert_eq!(line_text(&lines[0]), "");
+        assert_eq!(line_text(&lines[1]), "0");
+        assert_eq!(line_text(&lines[4]), "  ↓ 7 more");
+
+        let lines = viewport.show(numbered_lines(10), 5, 5);
+        assert_eq!(line_text(&lines[0]), "  ↑ 3 more");
+        assert_eq!(line_text(&lines[3]), "5");
+        assert_eq!(line_text(&lines[4]), "  ↓ 4 more");
+
+        // Moving up within the visible rows doesn't scroll
+        let lines = viewport.show(numbered_lines(10), 4, 5);
+        assert_eq!(line_text(&lines[1]), "3");
+
+        let lines = viewport.show(numbered_lines(10), 9, 5);
+        assert_eq!(line_text(&lines[3]), "9");
+        assert_eq!(line_text(&lines[4]), "");
+    }
+
+    #[test]
+    fn test_viewport_adjusts_to_resize() {
+        let viewport = Viewport::default();
+        viewport.show(numbered_lines(10), 9, 5);
+        assert_eq!(viewport.offset.get(), 7);
+
+        // Taller terminal: scroll back so no rows are wasted
+        let lines = viewport.show(numbered_lines(10), 9, 8);
+        assert_eq!(viewport.offset.get(), 4);
+        assert_eq!(line_text(&lines[6]), "9");
+
+        // Shorter terminal: cursor stays in view
+        let lines = viewport.show(numbered_lines(10), 9, 2);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(line_text(&lines[1]), "9");
     }
 
     #[test]
-    fn test_command_with_placeholder_no_active() {
-        let config = make_config(vec![make_text_step("path", None)]);
-        let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
-        wizard.text_buffer = "/tmp".to_string();
-        wizard.save_answer();
-
-        let cmd = wizard.command_with_placeholder("ignored");
-        assert_eq!(cmd, "ls /tmp");
+    fn test_page_and_home_end_keys_move_cursor() {
+        let options: Vec<String> = (0..20).map(|i| format!("Option {}", i)).collect();
+        let step = make_choice_step("opt", options.iter().map(|o| (o.as_str(), None)).collect());
+        let mut wizard = Wizard::new(make_config(vec![step]), vec!["cmd".to_string()]);
+        wizard.init_step();
+        wizard.viewport.rows.set(6);
+
+        wizard.move_cursor(wizard.page_size());
+        assert_eq!(wizard.choice_index, 6);
+        wizard.move_cursor(isize::MAX);
+        assert_eq!(wizard.choice_index, 19);
+        wizard.move_cursor(-wizard.page_size());
+        assert_eq!(wizard.choice_index, 13);
+        wizard.move_cursor(isize::MIN);
+        assert_eq!(wizard.choice_index, 0);
     }
 
     // ====================
-    // Preset tests
+    // Review tests
     // ====================
 
+    /// mode (Simple/Advanced) → level, shown only for Advanced → name
+    fn make_review_wizard() -> Wizard {
+        let mut level = make_choice_step("level", vec![("Low", Some("-l")), ("High", Some("-h"))]);
+        level.when = Some(HashMap::from([(
+            "mode".to_string(),
+            "Advanced".to_string(),
+        )]));
+        let config = make_config(vec![
+            make_choice_step("mode", vec![("Simple", None), ("Advanced", Some("--adv"))]),
+            level,
+            make_text_step("name", Some("--name")),
+        ]);
+        let mut wizard = Wizard::new(config, vec!["tool".to_string()]);
+        wizard.init_step();
+        wizard
+    }
+
     #[test]
-    fn test_wizard_with_presets_starts_in_menu() {
-        let mut config = make_config(vec![make_toggle_step("a", "-a")]);
-        config.presets = vec![Preset {
-            label: "Quick".to_string(),
-            flags: "-la".to_string(),
-        }];
+    fn test_changed_answer_prunes_hidden_steps() {
+        let mut wizard = make_review_wizard();
+        wizard.choice_index = 1;
+        wizard.next_step();
+        wizard.choice_index = 1;
+        wizard.next_step();
+        assert_eq!(wizard.build_command(), "tool --adv -h");
 
-        let wizard = Wizard::new(config, vec!["ls".to_string()]);
-        assert_eq!(wizard.phase, Phase::Menu);
+        // Back to the first step, switch to Simple
+        wizard.current_step = 0;
+        wizard.choice_index = 0;
+        wizard.save_answer();
+        assert!(!wizard.answers.contains_key("level"));
+        assert_eq!(wizard.build_command(), "tool");
     }
 
     #[test]
-    fn test_wizard_without_presets_starts_in_steps() {
-        let config = make_config(vec![make_toggle_step("a", "-a")]);
-        let wizard = Wizard::new(config, vec!["ls".to_string()]);
+    fn test_review_jumps_to_step_and_returns() {
+        let mut wizard = make_review_wizard();
+        wizard.next_step();
+        wizard.text_buffer.set("web");
+        wizard.next_step();
+        assert_eq!(wizard.phase, Phase::Confirm);
+
+        wizard.open_review();
+        assert_eq!(wizard.list_labels(), vec!["Choose mode:", "Enter name:"]);
+
+        wizard.review_index = 1;
+        wizard.edit_reviewed_step();
         assert_eq!(wizard.phase, Phase::Steps);
-    }
+        assert_eq!(wizard.text_buffer.as_str(), "web");
 
-    #[test]
-    fn test_build_preset_command() {
-        let mut config = make_config(vec![]);
-        config.presets = vec![
-            Preset {
-                label: "Quick".to_string(),
-                flags: "-la".to_string(),
-            },
-            Preset {
-                label: "Verbose".to_string(),
-                flags: "-lah".to_string(),
-            },
-        ];
+        wizard.text_buffer.set("api");
+        assert_eq!(wizard.finish_review_edit(), None);
+        assert_eq!(wizard.phase, Phase::Review);
+        assert_eq!(wizard.review_index, 1);
 
-        let mut wizard = Wizard::new(config, vec!["ls".to_string()]);
+        wizard.close_review();
+        assert_eq!(wizard.phase, Phase::Confirm);
+        assert_eq!(wizard.confirm_command(), "tool --name api");
+    }
 
-        // Select first preset
-        wizard.menu_index = 1;
-        assert_eq!(wizard.build_preset_command(), Some("ls -la".to_string()));
+    #[test]
+    fn test_discarded_review_edit_stops_reviewing() {
+        let mut wizard = make_review_wizard();
+        wizard.next_step();
+        wizard.text_buffer.set("web");
+        wizard.next_step();
 
-        // Select second preset
-        wizard.menu_index = 2;
-        assert_eq!(wizard.build_preset_command(), Some("ls -lah".to_string()));
+        // Enter on a step from review, Esc back, Esc to Confirm, Esc to the last step
+        wizard.open_review();
+        wizard.review_index = 1;
+        wizard.edit_reviewed_step();
+        wizard.discard_review_edit();
+        assert_eq!(wizard.phase, Phase::Review);
+        wizard.close_review();
+        assert_eq!(wizard.phase, Phase::Confirm);
+        wizard.prev_step();
+        assert_eq!(wizard.phase, Phase::Steps);
+        assert!(!wizard.reviewing);
     }
 
     #[test]
-    fn test_menu_item_count() {
-        let mut config = make_config(vec![]);
-        config.presets = vec![
-            Preset {
-                label: "A".to_string(),
-                flags: "-a".to_string(),
-            },
-            Preset {
-                label: "B".to_string(),
-                flags: "-b".to_string(),
-            },
-        ];
+    fn test_review_edit_asks_revealed_steps() {
+        let mut wizard = make_review_wizard();
+        wizard.next_step();
+        wizard.next_step();
 
-        let wizard = Wizard::new(config, vec!["ls".to_string()]);
-        assert_eq!(wizard.menu_item_count(), 3); // wizard + 2 presets
+        wizard.open_review();
+        wizard.edit_reviewed_step();
+        wizard.choice_index = 1; // Advanced reveals "level"
+        wizard.finish_review_edit();
+        assert_eq!(wizard.phase, Phase::Steps);
+        assert_eq!(wizard.current_step().unwrap().id, "level");
+
+        wizard.choice_index = 0;
+        wizard.finish_review_edit();
+        assert_eq!(wizard.phase, Phase::Review);
+        assert_eq!(wizard.build_command(), "tool --adv -l");
     }
 
     // ====================
@@ -1918,7 +4677,7 @@ mod tests {
     #[test]
     fn test_get_current_chain() {
         let mut step = make_choice_step("action", vec![("Run", None), ("Build", None)]);
-        step.options[0].chain = Some("docker-run".to_string());
+        step.options[0].chain = Some("docker-run".into());
 
         let config = make_config(vec![step]);
         let mut wizard = Wizard::new(config, vec!["docker".to_string()]);
@@ -1926,7 +4685,7 @@ mod tests {
 
         // Select "Run" which has a chain
         wizard.choice_index = 0;
-        assert_eq!(wizard.get_current_chain(), Some("docker-run".to_string()));
+        assert_eq!(wizard.get_current_chain(), Some("docker-run".into()));
 
         // Select "Build" which has no chain
         wizard.choice_index = 1;
@@ -1936,7 +4695,7 @@ mod tests {
     #[test]
     fn test_next_step_returns_chain() {
         let mut step = make_choice_step("action", vec![("Run", None), ("Build", None)]);
-        step.options[0].chain = Some("docker-run".to_string());
+        step.options[0].chain = Some("docker-run".into());
 
         let config = make_config(vec![step]);
         let mut wizard = Wizard::new(config, vec!["docker".to_string()]);
@@ -1944,7 +4703,188 @@ mod tests {
         wizard.choice_index = 0;
 
         let chain = wizard.next_step();
-        assert_eq!(chain, Some("docker-run".to_string()));
+        assert_eq!(chain, Some("docker-run".into()));
+    }
+
+    /// A kubectl-like wizard: a namespace, then an action chaining to `logs`
+    fn make_chaining_wizard(chain: Chain) -> Wizard {
+        let mut action = make_choice_step("action", vec![("Logs", None), ("Get", Some("get"))]);
+        action.options[0].chain = Some(chain);
+        let mut config = make_config(vec![make_text_step("namespace", Some("-n")), action]);
+        config.command = "kubectl".to_string();
+        let mut wizard = Wizard::new(config, vec!["kubectl".to_string()]);
+        wizard.init_step();
+        wizard.text_buffer.set("prod");
+        wizard.next_step();
+        wizard
+    }
+
+    fn make_logs_config() -> Config {
+        let mut config = make_config(vec![
+            make_text_step("pod", None),
+            make_toggle_step("follow", "-f"),
+        ]);
+        config.command = "kubectl logs".to_string();
+        config
+    }
+
+    #[test]
+    fn test_open_chain_carries_flags() {
+        let chain = Chain {
+            flags: vec!["namespace".to_string()],
+            ..Chain::from("kubectl-logs")
+        };
+        let mut wizard = make_chaining_wizard(chain.clone());
+        let history = History::new(None);
+
+        let child = wizard.open_chain(&chain, make_logs_config(), &history);
+        assert_eq!(child.base_command, ["kubectl", "-n", "prod", "logs"]);
+
+        // A command that doesn't extend this one gets the flags at the end
+        let chain = Chain {
+            command: Some("stern".to_string()),
+            ..chain
+        };
+        let child = wizard.open_chain(&chain, make_logs_config(), &history);
+        assert_eq!(child.base_command, ["stern", "-n", "prod"]);
+
+        // Steps not listed, or not answered, add nothing
+        wizard.answers.remove("namespace");
+        let child = wizard.open_chain(&chain, make_logs_config(), &history);
+        assert_eq!(child.base_command, ["stern"]);
+    }
+
+    #[test]
+    fn test_save_preset_from_chain_keeps_config_command() {
+        let chain = Chain {
+            flags: vec!["namespace".to_string()],
+            ..Chain::from("kubectl-logs")
+        };
+        let mut wizard = make_chaining_wizard(chain.clone());
+        wizard
+            .answers
+            .insert("namespace".to_string(), Answer::Text("my ns".to_string()));
+        let mut logs = wizard.open_chain(&chain, make_logs_config(), &History::new(None));
+        logs.answers
+            .insert("follow".to_string(), Answer::Toggle(true));
+
+        let dir =
+            std::env::temp_dir().join(format!("i-wizard-chain-preset-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("base.json");
+        std::fs::write(&base, r#"{ "command": "kubectl logs" }"#).unwrap();
+        let overlay = dir.join("kubectl-logs.json");
+
+        logs.text_buffer.set("Follow");
+        logs.save_preset_with(|preset| {
+            config::add_preset(&overlay, preset)?;
+            Ok(overlay.clone())
+        });
+        let (config, _) = config::merge_layers(&[
+            (config::Source::Project, base),
+            (config::Source::User, overlay.clone()),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.command, "kubectl logs");
+        assert_eq!(config.presets[0].flags, "-f");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_open_chain_two_levels_keeps_earlier_flags() {
+        let chain = Chain {
+            flags: vec!["namespace".to_string()],
+            ..Chain::from("kubectl-logs")
+        };
+        let mut wizard = make_chaining_wizard(chain.clone());
+        wizard
+            .answers
+            .insert("namespace".to_string(), Answer::Text("my ns".to_string()));
+        let history = History::new(None);
+        let mut logs = wizard.open_chain(&chain, make_logs_config(), &history);
+        logs.answers
+            .insert("follow".to_string(), Answer::Toggle(true));
+
+        let chain = Chain {
+            flags: vec!["follow".to_string()],
+            ..Chain::from("kubectl-logs-web")
+        };
+        let mut web = make_config(vec![make_toggle_step("previous", "-p")]);
+        web.command = "kubectl logs web".to_string();
+        let child = logs.open_chain(&chain, web, &history);
+        assert_eq!(
+            child.base_command,
+            ["kubectl", "-n", "my ns", "logs", "-f", "web"]
+        );
+        let cmd = child.command_line(&child.build_parts());
+        assert_eq!(cmd.display, "kubectl -n 'my ns' logs -f web");
+        assert_eq!(cmd.argv.unwrap(), child.base_command);
+    }
+
+    #[test]
+    fn test_open_chain_preselects_answers() {
+        let mut chain = Chain::from("kubectl-logs");
+        chain.answers = HashMap::from([
+            ("pod".to_string(), "{{namespace}}-web".to_string()),
+            ("follow".to_string(), "maybe".to_string()),
+        ]);
+        let wizard = make_chaining_wizard(chain.clone());
+
+        let mut child = wizard.open_chain(&chain, make_logs_config(), &History::new(None));
+        // Pre-filled, but still asked
+        assert_eq!(child.phase, Phase::Steps);
+        assert_eq!(child.current_step, 0);
+        assert_eq!(child.text_buffer.as_str(), "prod-web");
+        // Not a yes/no answer, so left to the user
+        assert!(!child.answers.contains_key("follow"));
+
+        child.next_step();
+        child.next_step();
+        assert_eq!(child.build_command(), "kubectl logs prod-web");
+    }
+
+    #[test]
+    fn test_open_chain_highlights_preset() {
+        let wizard = make_chaining_wizard(Chain::from("kubectl-logs"));
+        let mut config = make_logs_config();
+        config.presets = vec![
+            Preset {
+                label: "Follow".to_string(),
+                flags: "-f <pod>".to_string(),
+            },
+            Preset {
+                label: "Last hour".to_string(),
+                flags: "--since 1h <pod>".to_string(),
+            },
+        ];
+        let chain = Chain {
+            preset: Some("Last hour".to_string()),
+            ..Chain::from("kubectl-logs")
+        };
+
+        let child = wizard.open_chain(&chain, config, &History::new(None));
+        assert_eq!(child.phase, Phase::Menu);
+        assert_eq!(
+            child.selected_preset().map(|p| p.label.as_str()),
+            Some("Last hour")
+        );
+    }
+
+    #[test]
+    fn test_chaining_keeps_wizard_state() {
+        let chain = Chain::from("kubectl-logs");
+        let mut wizard = make_chaining_wizard(chain.clone());
+        let child = wizard.next_step();
+        assert_eq!(child, Some(chain));
+
+        // Coming back, the wizard is where it chained from
+        assert_eq!(wizard.phase, Phase::Steps);
+        assert_eq!(wizard.current_step, 1);
+        assert_eq!(wizard.choice_index, 0);
+        wizard.prev_step();
+        assert_eq!(wizard.text_buffer.as_str(), "prod");
     }
 
     // ==============================
@@ -1993,6 +4933,41 @@ mod tests {
         assert_eq!(cmd, Some("curl -s 'https://example.com'".to_string()));
     }
 
+    #[test]
+    fn test_preset_placeholder_escaped_for_its_quotes() {
+        let mut config = make_config(vec![]);
+        config.presets = vec![Preset {
+            label: "Find".to_string(),
+            flags: ". -name '<pattern>' -newer <file>".to_string(),
+        }];
+
+        let mut wizard = Wizard::new(config, vec!["find".to_string()]);
+        wizard.menu_index = 1;
+        wizard
+            .preset_placeholder_values
+            .insert("<pattern>".to_string(), "it's*".to_string());
+        wizard
+            .preset_placeholder_values
+            .insert("<file>".to_string(), "$(rm -rf ~)".to_string());
+
+        assert_eq!(
+            wizard.build_preset_command(),
+            Some(r"find . -name 'it'\''s*' -newer '$(rm -rf ~)'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_picked_placeholder_value_is_quoted() {
+        let mut wizard = make_preset_wizard("-f <pod>", &[("<pod>", "printf 'x'")]);
+        wizard.phase = Phase::Confirm;
+        wizard.prepare_confirm_phase();
+        wait_for_fetch(&mut wizard);
+        assert_eq!(
+            output::display(&wizard.command_with_placeholder("web; reboot")),
+            "kubectl logs -f 'web; reboot'"
+        );
+    }
+
     #[test]
     fn test_preset_placeholder_navigation() {
         let mut config = make_config(vec![]);
@@ -2013,7 +4988,7 @@ mod tests {
         );
 
         // Fill first placeholder and advance
-        wizard.text_buffer = "hello".to_string();
+        wizard.text_buffer.set("hello");
         let done = wizard.next_preset_placeholder();
         assert!(!done); // Not done yet
         assert_eq!(wizard.preset_placeholder_index, 1);
@@ -2023,7 +4998,7 @@ mod tests {
         );
 
         // Fill second placeholder and advance
-        wizard.text_buffer = "https://api.example.com".to_string();
+        wizard.text_buffer.set("https://api.example.com");
         let done = wizard.next_preset_placeholder();
         assert!(done); // All done
 
@@ -2038,6 +5013,260 @@ mod tests {
         );
     }
 
+    // ====================
+    // Headless answer tests
+    // ====================
+
+    fn answers(pairs: &[(&str, &str)]) -> Vec<(String, AnswerValue)> {
+        pairs
+            .iter()
+            .map(|(id, value)| (id.to_string(), AnswerValue::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_answers_builds_command() {
+        let config = make_config(vec![
+            make_choice_step(
+                "mode",
+                vec![("Interactive", Some("-it")), ("Detached", Some("-d"))],
+            ),
+            make_toggle_step("cleanup", "--rm"),
+            make_text_step("name", Some("--name")),
+        ]);
+        let cmd = run_headless(
+            config,
+            vec!["docker".to_string(), "run".to_string()],
+            &answers(&[("mode", "Detached"), ("cleanup", "true"), ("name", "web")]),
+        )
+        .unwrap();
+        assert_eq!(cmd.display, "docker run -d --rm --name web");
+    }
+
+    #[test]
+    fn test_typed_values_are_their_own_argv_words() {
+        let config = make_config(vec![make_text_step("message", Some("-m"))]);
+        let git = vec!["git".to_string(), "commit".to_string()];
+        let cmd = run_headless(
+            config.clone(),
+            git.clone(),
+            &answers(&[("message", "<wip> | tee x")]),
+        )
+        .unwrap();
+        assert_eq!(cmd.display, "git commit -m '<wip> | tee x'");
+        assert_eq!(cmd.argv.unwrap(), ["git", "commit", "-m", "<wip> | tee x"]);
+
+        // A glob needs a shell, which only a config that opts in gets
+        let glob = answers(&[("message", "*.md")]);
+        let cmd = run_headless(config.clone(), git.clone(), &glob).unwrap();
+        assert_eq!(cmd.display, "git commit -m *.md");
+        assert!(!cmd.runnable());
+        let shell_config = Config {
+            shell: true,
+            ..config
+        };
+        assert!(run_headless(shell_config, git, &glob).unwrap().runnable());
+    }
+
+    #[test]
+    fn test_headless_argv_keeps_flag_and_value_apart() {
+        let config = make_config(vec![
+            make_text_step("message", Some("-m")),
+            make_text_step("file", None),
+        ]);
+        let cmd = run_headless(
+            config,
+            vec!["git".to_string(), "commit".to_string()],
+            &answers(&[("message", "it's $HOME"), ("file", "a b.txt")]),
+        )
+        .unwrap();
+        assert_eq!(
+            cmd.argv,
+            Some(
+                ["git", "commit", "-m", "it's $HOME", "a b.txt"]
+                    .map(String::from)
+                    .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_headless_shell_config_has_no_argv() {
+        let mut config = make_config(vec![make_text_step("file", None)]);
+        let cmd = run_headless(
+            config.clone(),
+            vec!["ls".to_string()],
+            &answers(&[("file", "*.log")]),
+        )
+        .unwrap();
+        // The glob needs the shell to expand it
+        assert_eq!(cmd.argv, None);
+
+        config.shell = true;
+        let cmd = run_headless(config, vec!["ls".to_string()], &[]).unwrap();
+        assert_eq!(cmd.display, "ls");
+        assert_eq!(cmd.argv, None);
+    }
+
+    #[test]
+    fn test_apply_answers_uses_defaults_for_missing_steps() {
+        let mut step =
+            make_choice_step("format", vec![("List", Some("-l")), ("Long", Some("-la"))]);
+        step.default = Some(1);
+        let config = make_config(vec![step, make_toggle_step("hidden", "-a")]);
+        let cmd = run_headless(config, vec!["ls".to_string()], &[]).unwrap();
+        assert_eq!(cmd.display, "ls -la");
+    }
+
+    #[test]
+    fn test_apply_answers_multi_labels() {
+        let config = make_config(vec![make_multi_step(
+            "options",
+            vec![("Long", "-l"), ("All", "-a"), ("Human", "-h")],
+        )]);
+        let cmd = run_headless(
+            config,
+            vec!["ls".to_string()],
+            &answers(&[("options", "Long, human")]),
+        )
+        .unwrap();
+        assert_eq!(cmd.display, "ls -l -h");
+    }
+
+    #[test]
+    fn test_apply_answers_list_keeps_commas_in_labels() {
+        let config = make_config(vec![make_multi_step(
+            "options",
+            vec![("Long, with sizes", "-lh"), ("All", "-a")],
+        )]);
+        let list =
+            |labels: &[&str]| AnswerValue::List(labels.iter().map(|l| l.to_string()).collect());
+        let cmd = run_headless(
+            config.clone(),
+            vec!["ls".to_string()],
+            &[("options".to_string(), list(&["Long, with sizes", "All"]))],
+        )
+        .unwrap();
+        assert_eq!(cmd.display, "ls -lh -a");
+
+        let config = make_config(vec![make_choice_step("mode", vec![("Long", Some("-l"))])]);
+        let err = run_headless(
+            config,
+            vec!["ls".to_string()],
+            &[("mode".to_string(), list(&["Long"]))],
+        );
+        assert_eq!(err.unwrap_err(), AnswerError::NotMulti("mode".to_string()));
+    }
+
+    #[test]
+    fn test_apply_answers_label_prefix() {
+        let config = make_config(vec![make_choice_step(
+            "mode",
+            vec![
+                ("Detached (background)", Some("-d")),
+                ("Foreground", None),
+                ("Foreground (no TTY)", Some("-t")),
+            ],
+        )]);
+        let wizard_cmd = |value: &str| {
+            run_headless(
+                config.clone(),
+                vec!["docker".to_string()],
+                &answers(&[("mode", value)]),
+            )
+            .map(|cmd| cmd.display)
+        };
+        assert_eq!(wizard_cmd("detached"), Ok("docker -d".to_string()));
+        // Exact match wins over a longer label sharing the prefix
+        assert_eq!(wizard_cmd("Foreground"), Ok("docker".to_string()));
+        // Ambiguous prefix is rejected
+        assert!(wizard_cmd("Fore").is_err());
+    }
+
+    #[test]
+    fn test_apply_answers_unknown_step() {
+        let config = make_config(vec![make_toggle_step("a", "-a")]);
+        let err = run_headless(config, vec!["ls".to_string()], &answers(&[("b", "true")]));
+        assert_eq!(err, Err(AnswerError::UnknownStep("b".to_string())));
+    }
+
+    #[test]
+    fn test_apply_answers_unknown_option() {
+        let config = make_config(vec![make_choice_step(
+            "format",
+            vec![("List", Some("-l")), ("Grid", None)],
+        )]);
+        let err = run_headless(
+            config,
+            vec!["ls".to_string()],
+            &answers(&[("format", "Table")]),
+        )
+        .unwrap_err();
+        assert!(matches!(err, AnswerError::UnknownOption { .. }));
+        assert!(err.to_string().contains("List, Grid"));
+    }
+
+    #[test]
+    fn test_apply_answers_invalid_toggle() {
+        let config = make_config(vec![make_toggle_step("hidden", "-a")]);
+        let err = run_headless(
+            config,
+            vec!["ls".to_string()],
+            &answers(&[("hidden", "maybe")]),
+        );
+        assert_eq!(
+            err,
+            Err(AnswerError::InvalidToggle(
+                "hidden".to_string(),
+                "maybe".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_apply_answers_hidden_step() {
+        let mut conditional_step = make_toggle_step("verbose", "-v");
+        let mut when = HashMap::new();
+        when.insert("mode".to_string(), "Advanced".to_string());
+        conditional_step.when = Some(when);
+
+        let config = make_config(vec![
+            make_choice_step("mode", vec![("Simple", None), ("Advanced", None)]),
+            conditional_step,
+        ]);
+
+        let err = run_headless(
+            config.clone(),
+            vec!["test".to_string()],
+            &answers(&[("mode", "Simple"), ("verbose", "true")]),
+        )
+        .unwrap_err();
+        assert!(matches!(err, AnswerError::HiddenStep(ref id, _) if id == "verbose"));
+        assert!(err.to_string().contains("mode = Advanced"));
+
+        let cmd = run_headless(
+            config,
+            vec!["test".to_string()],
+            &answers(&[("mode", "Advanced"), ("verbose", "true")]),
+        )
+        .unwrap();
+        assert_eq!(cmd.display, "test -v");
+    }
+
+    #[test]
+    fn test_apply_answers_rejects_chain() {
+        let mut step = make_choice_step("action", vec![("Run", None), ("Build", None)]);
+        step.options[0].chain = Some("docker-run".into());
+        let config = make_config(vec![step]);
+
+        let err = run_headless(
+            config,
+            vec!["docker".to_string()],
+            &answers(&[("action", "Run")]),
+        );
+        assert!(matches!(err, Err(AnswerError::Chain(_, _, ref chain)) if chain == "docker-run"));
+    }
+
     // ====================
     // Command exists tests
     // ====================